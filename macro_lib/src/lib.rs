@@ -1,7 +1,7 @@
 
 use quote::quote;
 use syn::{
-    parse_macro_input, DeriveInput
+    parse_macro_input, DeriveInput, Data, Fields, LitChar
 };
 
 #[proc_macro_derive(Grid2D)]
@@ -64,3 +64,74 @@ pub fn derive_grid_2d(input: proc_macro::TokenStream) -> proc_macro::TokenStream
 
     proc_macro::TokenStream::from(expanded)
 }
+
+/// Char conversions for an enum of grid cells, each variant being annotated with its char: `#[cell('#')]`.
+/// Generates `TryFrom<char>` (with `Error` as error type), `From<Self> for char` and `Display`.
+#[proc_macro_derive(GridCell, attributes(cell))]
+pub fn derive_grid_cell(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let variants = match &input.data {
+        Data::Enum(data) => &data.variants,
+        _ => return syn::Error::new_spanned(&input.ident, "GridCell can only be derived for enums")
+            .to_compile_error()
+            .into(),
+    };
+
+    let mut idents = Vec::new();
+    let mut chars = Vec::new();
+    for variant in variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return syn::Error::new_spanned(variant, "GridCell variants can't have fields")
+                .to_compile_error()
+                .into();
+        }
+        let attr = variant.attrs.iter().find(|attr| attr.path.is_ident("cell"));
+        let c = match attr.map(|attr| attr.parse_args::<LitChar>()) {
+            Some(Ok(c)) => c,
+            Some(Err(e)) => return e.to_compile_error().into(),
+            None => return syn::Error::new_spanned(variant, "Missing #[cell('c')] attribute")
+                .to_compile_error()
+                .into(),
+        };
+        idents.push(&variant.ident);
+        chars.push(c);
+    }
+
+    let expanded = quote! {
+        impl std::convert::TryFrom<char> for #name {
+            type Error = Error;
+
+            fn try_from(c: char) -> std::result::Result<Self, Self::Error>
+            {
+                match c {
+                    #( #chars => Ok(Self::#idents), )*
+                    _ => Err(Error::new_token(0, 1)),
+                }
+            }
+        }
+
+        impl std::convert::From<#name> for char {
+            fn from(cell: #name) -> Self
+            {
+                match cell {
+                    #( #name::#idents => #chars, )*
+                }
+            }
+        }
+
+        impl std::fmt::Display for #name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+            {
+                let c = match self {
+                    #( Self::#idents => #chars, )*
+                };
+                write!(f, "{}", c)
+            }
+        }
+    };
+
+    proc_macro::TokenStream::from(expanded)
+}
@@ -0,0 +1,37 @@
+use macro_lib::GridCell;
+
+#[derive(Debug, PartialEq)]
+struct Error {
+    line_pos: usize,
+    token_len: usize
+}
+
+impl Error {
+    fn new_token(line_pos: usize, token_len: usize) -> Self {
+        Self { line_pos, token_len }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, GridCell)]
+enum Cell {
+    #[cell('.')]
+    Empty,
+    #[cell('#')]
+    Wall,
+    #[cell('>')]
+    Arrow
+}
+
+#[test]
+fn conversions()
+{
+    assert_eq!(Cell::try_from('.'), Ok(Cell::Empty));
+    assert_eq!(Cell::try_from('>'), Ok(Cell::Arrow));
+    assert_eq!(Cell::try_from('x'), Err(Error::new_token(0, 1)));
+    assert_eq!(char::from(Cell::Wall), '#');
+    let c: char = Cell::Empty.into();
+    assert_eq!(c, '.');
+
+    let line: String = [Cell::Wall, Cell::Empty, Cell::Arrow].iter().map(|cell| cell.to_string()).collect();
+    assert_eq!(line, "#.>");
+}
@@ -12,8 +12,95 @@ fn main() -> Result<()> {
             .required(true)
             .takes_value(true)
         )
+        .arg(
+            Arg::new("verbose")
+            .long("verbose")
+            .short('v')
+            .help("Print intermediate results, such as drawings")
+        )
+        .arg(
+            Arg::new("trace")
+            .long("trace")
+            .help("Print every step of the computation, for the days supporting it")
+        )
+        .arg(
+            Arg::new("render")
+            .long("render")
+            .help("Draw the final state of the day to a PPM (or .pgm) image")
+            .takes_value(true)
+            .conflicts_with_all(&["animate", "stop-at", "snapshot", "mesh", "dot"])
+        )
+        .arg(
+            Arg::new("mesh")
+            .long("mesh")
+            .help("Export the shapes of the day to a Wavefront OBJ (or .stl) file")
+            .takes_value(true)
+            .conflicts_with_all(&["animate", "stop-at", "snapshot", "dot"])
+        )
+        .arg(
+            Arg::new("exterior-only")
+            .long("exterior-only")
+            .help("Only export the faces which can be seen from the outside")
+            .requires("mesh")
+        )
+        .arg(
+            Arg::new("dot")
+            .long("dot")
+            .help("Write the graphs of the day to a Graphviz DOT file")
+            .takes_value(true)
+            .conflicts_with_all(&["animate", "stop-at", "snapshot"])
+        )
+        .arg(
+            Arg::new("scale")
+            .long("scale")
+            .help("Size in pixels of a cell of the rendered image")
+            .takes_value(true)
+            .default_value("1")
+            .value_parser(clap::value_parser!(usize))
+        )
+        .arg(
+            Arg::new("animate")
+            .long("animate")
+            .help("Draw every step of the simulation in the terminal")
+        )
+        .arg(
+            Arg::new("fps")
+            .long("fps")
+            .help("Frame rate of the animation")
+            .takes_value(true)
+            .default_value("10")
+            .value_parser(clap::value_parser!(f64))
+        )
+        .arg(
+            Arg::new("stop-at")
+            .long("stop-at")
+            .help("Stop the simulation after this many steps")
+            .takes_value(true)
+            .value_parser(clap::value_parser!(usize))
+        )
+        .arg(
+            Arg::new("snapshot")
+            .long("snapshot")
+            .help("Write the state of the simulation at this step to a file, can be repeated")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .value_parser(clap::value_parser!(usize))
+        )
+        .arg(
+            Arg::new("snapshot-dir")
+            .long("snapshot-dir")
+            .help("Where the snapshots are written")
+            .takes_value(true)
+            .default_value(".")
+        )
         .get_matches();
 
+    days::set_verbosity(match () {
+        _ if matches.contains_id("trace") => days::Verbosity::Trace,
+        _ if matches.contains_id("verbose") => days::Verbosity::Verbose,
+        _ => days::Verbosity::Quiet,
+    });
+
     let map_day_str_to_fn = BTreeMap::from(
         days::DAYS_FUNCS_NAMES_AND_PTRS
     );
@@ -22,7 +109,55 @@ fn main() -> Result<()> {
 
     let mut day_input_file = get_input_file(day_str)?;
 
-    day_fn(&mut day_input_file).map_err(|err| {
+    let simulating = matches.contains_id("animate")
+        || matches.contains_id("stop-at")
+        || matches.contains_id("snapshot");
+
+    let res = match matches.get_one::<String>("render") {
+        _ if simulating => {
+            let map_day_str_to_simulate_fn = BTreeMap::from(days::DAYS_SIMULATE_FUNCS);
+            let simulate_fn = map_day_str_to_simulate_fn.get(day_str.as_str())
+                .with_context(|| format!("{day_str} is not a step by step simulation"))?;
+            let options = days::simulation::RunOptions {
+                animate: matches.contains_id("animate"),
+                frame_rate: *matches.get_one::<f64>("fps").unwrap(),
+                stop_at: matches.get_one::<usize>("stop-at").copied(),
+                snapshot_steps: matches.get_many::<usize>("snapshot").map(|steps| steps.copied().collect()).unwrap_or_default(),
+                snapshot_dir: matches.get_one::<String>("snapshot-dir").unwrap().into(),
+            };
+            simulate_fn(&mut day_input_file, &options)
+        },
+        _ if matches.contains_id("mesh") => {
+            let map_day_str_to_mesh_fn = BTreeMap::from(days::DAYS_MESH_FUNCS);
+            let mesh_fn = map_day_str_to_mesh_fn.get(day_str.as_str())
+                .with_context(|| format!("{day_str} has no shape to export"))?;
+            let target = days::mesh::MeshTarget {
+                path: matches.get_one::<String>("mesh").unwrap().into(),
+                exterior_only: matches.contains_id("exterior-only"),
+            };
+            mesh_fn(&mut day_input_file, &target)
+        },
+        _ if matches.contains_id("dot") => {
+            let map_day_str_to_dot_fn = BTreeMap::from(days::DAYS_DOT_FUNCS);
+            let dot_fn = map_day_str_to_dot_fn.get(day_str.as_str())
+                .with_context(|| format!("{day_str} has no graph to export"))?;
+            let path = matches.get_one::<String>("dot").unwrap();
+            dot_fn(&mut day_input_file, path.as_ref())
+        },
+        Some(path) => {
+            let map_day_str_to_render_fn = BTreeMap::from(days::DAYS_RENDER_FUNCS);
+            let render_fn = map_day_str_to_render_fn.get(day_str.as_str())
+                .with_context(|| format!("{day_str} does not support rendering"))?;
+            let target = days::image::RenderTarget {
+                path: path.into(),
+                scale: *matches.get_one::<usize>("scale").unwrap(),
+            };
+            render_fn(&mut day_input_file, &target)
+        },
+        None => day_fn(&mut day_input_file),
+    };
+
+    res.map_err(|err| {
         match err {
             days::Error::Parsing(parsing) => anyhow!(get_parsing_error_msg(parsing)),
             days::Error::ParsingWithVerboseErrorMessage(msg) => anyhow!(msg),
@@ -35,26 +170,24 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn get_input_file(day_str: &String) -> Result<File>
+fn get_input_file(day_str: &str) -> Result<File>
 {
     let day_input_filepath_1 = format!("inputs/{day_str}.txt");
-    let day_input_file = File::open(&day_input_filepath_1);
-    if day_input_file.is_ok() {
-        return Ok(day_input_file.unwrap());
+    if let Ok(day_input_file) = File::open(&day_input_filepath_1) {
+        return Ok(day_input_file);
     }
     let day_input_filepath_2 = format!("inputs/{}.txt", strip_part(day_str)?);
-    let day_input_file = File::open(&day_input_filepath_2);
-    if day_input_file.is_ok() {
-        return Ok(day_input_file.unwrap());
+    if let Ok(day_input_file) = File::open(&day_input_filepath_2) {
+        return Ok(day_input_file);
     }
 
     Err(anyhow!("Cannot open input file (either {} or {})", day_input_filepath_1, day_input_filepath_2))
 }
 
-fn strip_part(day_str: &String) -> Result<String>
+fn strip_part(day_str: &str) -> Result<String>
 {
     let part_pos = day_str.find("_part_").ok_or(anyhow!("No _part_ in day_str"))?;
-    Ok(format!("{}", &day_str[0..part_pos]))
+    Ok(day_str[0..part_pos].to_string())
 }
 
 fn get_parsing_error_msg(parsing: days::error::Parsing) -> String {
@@ -0,0 +1,262 @@
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use crate::days::{Error, Result};
+
+/// Solution explored by `anneal`, higher scores are better
+pub trait AnnealingState {
+    /// Apply a random modification
+    fn neighbor(&mut self, rng: &mut StdRng);
+    /// Revert the last call to `neighbor`
+    fn undo(&mut self);
+    fn score(&self) -> f64;
+}
+
+/// How the temperature evolves after each epoch
+#[derive(Debug, Clone, Copy)]
+pub enum Cooling {
+    /// Multiplied by `factor`, which must be below 1
+    Geometric { factor: f64 },
+    /// Decreased by `step`
+    Linear { step: f64 },
+    /// Geometric, but set back to `reheat_temp` when the best score has not improved for `patience` epochs,
+    /// at most `max_reheats` times per run
+    AdaptiveReheat { factor: f64, patience: usize, reheat_temp: f64, max_reheats: usize },
+}
+
+#[derive(Debug, Clone)]
+pub struct AnnealingParams {
+    pub initial_temp: f64,
+    /// A run stops once the temperature goes below this one
+    pub final_temp: f64,
+    pub cooling: Cooling,
+    /// Number of moves tried at each temperature
+    pub epoch_length: usize,
+    /// Additional runs starting from the best state found so far
+    pub restarts: usize,
+    pub seed: u64,
+}
+
+impl Default for AnnealingParams {
+    fn default() -> Self
+    {
+        Self {
+            initial_temp: 2000.0,
+            final_temp: 2.0,
+            cooling: Cooling::Geometric { factor: 0.995 },
+            epoch_length: 100,
+            restarts: 0,
+            seed: 54321,
+        }
+    }
+}
+
+impl AnnealingParams {
+    /// Error if the temperature would never go below `final_temp`
+    pub fn validate(&self) -> Result<()>
+    {
+        let cools_down = match self.cooling {
+            Cooling::Geometric { factor } | Cooling::AdaptiveReheat { factor, .. } =>
+                factor > 0.0 && factor < 1.0 && self.final_temp > 0.0,
+            Cooling::Linear { step } => step > 0.0 && self.final_temp.is_finite(),
+        };
+        if !cools_down || !self.initial_temp.is_finite() {
+            return Err(Error::Unsupported(format!("annealing never ending with {:?} from {} to {}",
+                self.cooling, self.initial_temp, self.final_temp)));
+        }
+        Ok(())
+    }
+}
+
+/// Given to the callback of `anneal` after every epoch
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    pub run: usize,
+    pub epoch: usize,
+    pub temp: f64,
+    pub score: f64,
+    pub best_score: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnnealingResult<S> {
+    pub best: S,
+    pub best_score: f64,
+    pub num_epochs: usize,
+}
+
+/// Simulated annealing from `initial`, keeping track of the best state ever reached.
+/// Fails if `params` are not valid, see `AnnealingParams::validate`.
+pub fn anneal<S, Callback>(initial: S, params: &AnnealingParams, mut on_epoch: Callback) -> Result<AnnealingResult<S>>
+where S: AnnealingState + Clone,
+Callback: FnMut(&Progress)
+{
+    params.validate()?;
+    let mut rng = StdRng::seed_from_u64(params.seed);
+    let mut best_score = initial.score();
+    let mut best = initial.clone();
+    let mut state = initial;
+    let mut num_epochs = 0;
+
+    for run in 0..=params.restarts {
+        if run > 0 {
+            state = best.clone();
+        }
+        let mut score = state.score();
+        let mut temp = params.initial_temp;
+        let mut epoch = 0;
+        let mut epochs_without_improvement = 0;
+        let mut num_reheats = 0;
+
+        while temp > params.final_temp {
+            let mut improved = false;
+            for _ in 0..params.epoch_length {
+                state.neighbor(&mut rng);
+                let next_score = state.score();
+                if !accept(score, next_score, temp, &mut rng) {
+                    state.undo();
+                    continue;
+                }
+                score = next_score;
+                if score > best_score {
+                    best_score = score;
+                    best = state.clone();
+                    improved = true;
+                }
+            }
+            epoch += 1;
+            num_epochs += 1;
+            on_epoch(&Progress { run, epoch, temp, score, best_score });
+
+            epochs_without_improvement = if improved { 0 } else { epochs_without_improvement + 1 };
+            temp = match params.cooling {
+                Cooling::Geometric { factor } => temp * factor,
+                Cooling::Linear { step } => temp - step,
+                Cooling::AdaptiveReheat { factor, patience, reheat_temp, max_reheats } => {
+                    if epochs_without_improvement >= patience && num_reheats < max_reheats {
+                        num_reheats += 1;
+                        epochs_without_improvement = 0;
+                        reheat_temp
+                    }
+                    else {
+                        temp * factor
+                    }
+                },
+            };
+        }
+    }
+
+    Ok(AnnealingResult { best, best_score, num_epochs })
+}
+
+// improvements are always accepted, degradations with a probability decreasing with the temperature
+fn accept(score: f64, next_score: f64, temp: f64, rng: &mut StdRng) -> bool
+{
+    if next_score > score {
+        return true;
+    }
+    let p = ((next_score - score) / temp).exp();
+    rng.gen_range(0.0..1.0) < p
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // find the subset of WEIGHTS closest to TARGET
+    const WEIGHTS: [i64; 10] = [31, 7, 18, 44, 5, 23, 12, 9, 27, 16];
+    const TARGET: i64 = 100;
+
+    #[derive(Clone)]
+    struct Subset {
+        selected: [bool; 10],
+        last_flip: usize,
+    }
+
+    impl AnnealingState for Subset {
+        fn neighbor(&mut self, rng: &mut StdRng) {
+            self.last_flip = rng.gen_range(0..self.selected.len());
+            self.selected[self.last_flip] = !self.selected[self.last_flip];
+        }
+
+        fn undo(&mut self) {
+            self.selected[self.last_flip] = !self.selected[self.last_flip];
+        }
+
+        fn score(&self) -> f64 {
+            let sum: i64 = WEIGHTS.iter().zip(self.selected).filter(|(_, s)| *s).map(|(w, _)| w).sum();
+            -((sum - TARGET).abs() as f64)
+        }
+    }
+
+    #[test]
+    fn test_anneal() {
+        let initial = Subset { selected: [false; 10], last_flip: 0 };
+        let params = AnnealingParams {
+            initial_temp: 20.0,
+            final_temp: 0.1,
+            epoch_length: 20,
+            ..Default::default()
+        };
+        let mut num_callbacks = 0;
+        let res = anneal(initial.clone(), &params, |progress| {
+            num_callbacks += 1;
+            assert!(progress.best_score >= progress.score);
+        }).unwrap();
+        assert_eq!(res.best_score, 0.0);
+        assert_eq!(res.best.score(), 0.0);
+        assert_eq!(res.num_epochs, num_callbacks);
+
+        // same seed, same outcome
+        let res_again = anneal(initial.clone(), &params, |_| ()).unwrap();
+        assert_eq!(res_again.best.selected, res.best.selected);
+        assert_eq!(res_again.num_epochs, res.num_epochs);
+    }
+
+    #[test]
+    fn test_cooling_schedules() {
+        let initial = Subset { selected: [false; 10], last_flip: 0 };
+        let params = AnnealingParams {
+            initial_temp: 10.0,
+            final_temp: 1.0,
+            cooling: Cooling::Linear { step: 1.0 },
+            epoch_length: 5,
+            restarts: 2,
+            ..Default::default()
+        };
+        let mut runs = Vec::new();
+        let res = anneal(initial.clone(), &params, |progress| runs.push(progress.run)).unwrap();
+        assert_eq!(res.num_epochs, 3 * 9);
+        assert_eq!(runs.iter().filter(|&&run| run == 2).count(), 9);
+
+        let params = AnnealingParams {
+            initial_temp: 10.0,
+            final_temp: 0.01,
+            cooling: Cooling::AdaptiveReheat { factor: 0.5, patience: 1, reheat_temp: 8.0, max_reheats: 3 },
+            epoch_length: 1,
+            seed: 1,
+            ..Default::default()
+        };
+        let mut temps = Vec::new();
+        anneal(initial.clone(), &params, |progress| temps.push(progress.temp)).unwrap();
+        let num_reheats = temps.windows(2).filter(|w| w[1] > w[0]).count();
+        assert_eq!(num_reheats, 3);
+        assert!(temps.windows(2).all(|w| w[1] == 8.0 || w[1] == w[0] * 0.5));
+    }
+
+    #[test]
+    fn test_endless_params() {
+        let initial = Subset { selected: [false; 10], last_flip: 0 };
+        let endless = [
+            (Cooling::Geometric { factor: 1.0 }, 1.0),
+            (Cooling::Geometric { factor: 0.9 }, -1.0),
+            (Cooling::Linear { step: 0.0 }, 1.0),
+            (Cooling::AdaptiveReheat { factor: 1.5, patience: 1, reheat_temp: 8.0, max_reheats: 3 }, 1.0),
+        ];
+        for (cooling, final_temp) in endless {
+            let params = AnnealingParams { cooling, final_temp, ..Default::default() };
+            assert!(matches!(anneal(initial.clone(), &params, |_| ()), Err(Error::Unsupported(_))));
+        }
+        let params = AnnealingParams { cooling: Cooling::Linear { step: 1.0 }, final_temp: -1.0, ..Default::default() };
+        assert!(params.validate().is_ok());
+    }
+}
@@ -0,0 +1,326 @@
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// Maximization problem explored by `solve`
+pub trait BranchAndBound {
+    type Node: Clone;
+    type Score: Copy + Ord;
+    /// Nodes with the same key can be compared with `dominates`, use () if dominance is not used
+    type DominanceKey: Eq + Hash;
+
+    fn branch(&self, node: &Self::Node) -> Vec<Self::Node>;
+    /// Score that no terminal node below `node` can exceed
+    fn upper_bound(&self, node: &Self::Node) -> Self::Score;
+    fn is_terminal(&self, node: &Self::Node) -> bool;
+    /// Score of a terminal node
+    fn score(&self, node: &Self::Node) -> Self::Score;
+
+    fn dominance_key(&self, _node: &Self::Node) -> Option<Self::DominanceKey>
+    {
+        None
+    }
+
+    /// True if the best terminal node below `a` is at least as good as the best one below `b`
+    fn dominates(&self, _a: &Self::Node, _b: &Self::Node) -> bool
+    {
+        false
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    DepthFirst,
+    /// Highest upper bound first
+    BestFirst,
+}
+
+#[derive(Debug, Clone)]
+pub struct Params {
+    pub order: Order,
+    /// Stop after exploring this many nodes
+    pub max_nodes: Option<usize>,
+    pub time_limit: Option<Duration>,
+}
+
+impl Default for Params {
+    fn default() -> Self
+    {
+        Self {
+            order: Order::DepthFirst,
+            max_nodes: None,
+            time_limit: None,
+        }
+    }
+}
+
+type NodeId = usize;
+
+#[derive(Debug, Clone)]
+struct ArenaNode<N, S> {
+    node: N,
+    parent: Option<NodeId>,
+    upper_bound: S,
+    // a dominating node was found after this one was queued
+    dominated: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Solution<N, S> {
+    nodes: Vec<ArenaNode<N, S>>,
+    best_node: Option<NodeId>,
+    pub best_score: Option<S>,
+    pub num_explored: usize,
+    /// False if a limit stopped the search, the best score may then not be optimal
+    pub complete: bool,
+}
+
+impl<N, S> Solution<N, S> {
+    /// Nodes from the root to the best terminal node
+    pub fn best_path(&self) -> Vec<&N>
+    {
+        let mut path = Vec::new();
+        let mut current = self.best_node;
+        while let Some(id) = current {
+            path.push(&self.nodes[id].node);
+            current = self.nodes[id].parent;
+        }
+        path.reverse();
+        path
+    }
+}
+
+/// Find the terminal node with the highest score below `root`
+pub fn solve<P>(problem: &P, root: P::Node, params: &Params) -> Solution<P::Node, P::Score>
+where P: BranchAndBound
+{
+    let start_time = Instant::now();
+    let mut solution = Solution {
+        nodes: Vec::new(),
+        best_node: None,
+        best_score: None,
+        num_explored: 0,
+        complete: true,
+    };
+    let mut todo = Todo::new(params.order);
+    let mut non_dominated: HashMap<P::DominanceKey, Vec<NodeId>> = HashMap::new();
+
+    let can_improve = |best_score: Option<P::Score>, upper_bound: P::Score| {
+        best_score.is_none_or(|best| upper_bound > best)
+    };
+
+    let root_upper_bound = problem.upper_bound(&root);
+    solution.nodes.push(ArenaNode {node: root, parent: None, upper_bound: root_upper_bound, dominated: false});
+    if problem.is_terminal(&solution.nodes[0].node) {
+        solution.best_score = Some(problem.score(&solution.nodes[0].node));
+        solution.best_node = Some(0);
+        return solution;
+    }
+    todo.push(0, root_upper_bound);
+
+    while let Some(node_id) = todo.pop() {
+        let reached_limit = params.max_nodes.is_some_and(|max| solution.num_explored >= max)
+            || params.time_limit.is_some_and(|limit| start_time.elapsed() >= limit);
+        if reached_limit {
+            solution.complete = false;
+            break;
+        }
+        let current = &solution.nodes[node_id];
+        if current.dominated || !can_improve(solution.best_score, current.upper_bound) {
+            continue;
+        }
+        solution.num_explored += 1;
+
+        for child in problem.branch(&current.node) {
+            let upper_bound = problem.upper_bound(&child);
+            if !can_improve(solution.best_score, upper_bound) {
+                continue;
+            }
+            let child_id = solution.nodes.len();
+            if problem.is_terminal(&child) {
+                // the upper bound of a terminal node may be above its score
+                let score = problem.score(&child);
+                if solution.best_score.is_none_or(|best| score > best) {
+                    solution.best_score = Some(score);
+                    solution.best_node = Some(child_id);
+                    solution.nodes.push(ArenaNode {node: child, parent: Some(node_id), upper_bound, dominated: false});
+                }
+                continue;
+            }
+            if let Some(key) = problem.dominance_key(&child) {
+                let others = non_dominated.entry(key).or_default();
+                if others.iter().any(|&other| problem.dominates(&solution.nodes[other].node, &child)) {
+                    continue;
+                }
+                others.retain(|&other| {
+                    let other_node = &mut solution.nodes[other];
+                    other_node.dominated = problem.dominates(&child, &other_node.node);
+                    !other_node.dominated
+                });
+                others.push(child_id);
+            }
+            solution.nodes.push(ArenaNode {node: child, parent: Some(node_id), upper_bound, dominated: false});
+            todo.push(child_id, upper_bound);
+        }
+    }
+
+    solution
+}
+
+enum Todo<S> {
+    Stack(Vec<NodeId>),
+    Heap(BinaryHeap<(S, NodeId)>),
+}
+
+impl<S: Ord> Todo<S> {
+    fn new(order: Order) -> Self
+    {
+        match order {
+            Order::DepthFirst => Self::Stack(Vec::new()),
+            Order::BestFirst => Self::Heap(BinaryHeap::new()),
+        }
+    }
+
+    fn push(&mut self, id: NodeId, upper_bound: S)
+    {
+        match self {
+            Self::Stack(stack) => stack.push(id),
+            // on ties, the most recent (usually deepest) node comes first
+            Self::Heap(heap) => heap.push((upper_bound, id)),
+        }
+    }
+
+    fn pop(&mut self) -> Option<NodeId>
+    {
+        match self {
+            Self::Stack(stack) => stack.pop(),
+            Self::Heap(heap) => heap.pop().map(|(_, id)| id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // 0/1 knapsack
+    struct Knapsack {
+        items: Vec<(u32, u32)>,
+        capacity: u32,
+    }
+
+    #[derive(Clone, Debug)]
+    struct Partial {
+        num_decided: usize,
+        weight: u32,
+        value: u32,
+    }
+
+    impl BranchAndBound for Knapsack {
+        type Node = Partial;
+        type Score = u32;
+        type DominanceKey = usize;
+
+        fn branch(&self, node: &Partial) -> Vec<Partial> {
+            let (weight, value) = self.items[node.num_decided];
+            let skip = Partial {num_decided: node.num_decided + 1, ..*node};
+            let mut children = vec![skip];
+            if node.weight + weight <= self.capacity {
+                children.push(Partial {num_decided: node.num_decided + 1, weight: node.weight + weight, value: node.value + value});
+            }
+            children
+        }
+
+        fn upper_bound(&self, node: &Partial) -> u32 {
+            node.value + self.items[node.num_decided..].iter().map(|(_, value)| value).sum::<u32>()
+        }
+
+        fn is_terminal(&self, node: &Partial) -> bool {
+            node.num_decided == self.items.len()
+        }
+
+        fn score(&self, node: &Partial) -> u32 {
+            node.value
+        }
+
+        fn dominance_key(&self, node: &Partial) -> Option<usize> {
+            Some(node.num_decided)
+        }
+
+        fn dominates(&self, a: &Partial, b: &Partial) -> bool {
+            a.weight <= b.weight && a.value >= b.value
+        }
+    }
+
+    fn knapsack() -> Knapsack {
+        Knapsack {
+            items: vec![(12, 4), (2, 2), (1, 1), (4, 10), (1, 2), (3, 5), (7, 6)],
+            capacity: 15,
+        }
+    }
+
+    #[test]
+    fn test_orders() {
+        let root = Partial {num_decided: 0, weight: 0, value: 0};
+        let depth_first = solve(&knapsack(), root.clone(), &Params::default());
+        assert_eq!(depth_first.best_score, Some(23));
+        assert!(depth_first.complete);
+
+        let best_first = solve(&knapsack(), root, &Params {order: Order::BestFirst, ..Default::default()});
+        assert_eq!(best_first.best_score, Some(23));
+
+        let path = best_first.best_path();
+        assert_eq!(path.len(), 8);
+        assert_eq!(path[0].num_decided, 0);
+        assert!(path.windows(2).all(|w| w[1].num_decided == w[0].num_decided + 1));
+        assert_eq!(path.last().unwrap().value, 23);
+        assert_eq!(path.last().unwrap().weight, 15);
+    }
+
+    // the root has terminal children only, with (score, upper bound) pairs
+    struct LooseBounds {
+        children: Vec<(u32, u32)>,
+    }
+
+    impl BranchAndBound for LooseBounds {
+        type Node = Option<usize>;
+        type Score = u32;
+        type DominanceKey = ();
+
+        fn branch(&self, _node: &Option<usize>) -> Vec<Option<usize>> {
+            (0..self.children.len()).map(Some).collect()
+        }
+
+        fn upper_bound(&self, node: &Option<usize>) -> u32 {
+            node.map_or(u32::MAX, |i| self.children[i].1)
+        }
+
+        fn is_terminal(&self, node: &Option<usize>) -> bool {
+            node.is_some()
+        }
+
+        fn score(&self, node: &Option<usize>) -> u32 {
+            node.map_or(0, |i| self.children[i].0)
+        }
+    }
+
+    #[test]
+    fn test_terminal_with_loose_bound() {
+        let problem = LooseBounds { children: vec![(10, 10), (5, 20), (7, 7)] };
+        for order in [Order::DepthFirst, Order::BestFirst] {
+            let res = solve(&problem, None, &Params {order, ..Default::default()});
+            assert_eq!(res.best_score, Some(10));
+            assert_eq!(res.best_path().last(), Some(&&Some(0)));
+        }
+    }
+
+    #[test]
+    fn test_limits() {
+        let root = Partial {num_decided: 0, weight: 0, value: 0};
+        let res = solve(&knapsack(), root, &Params {max_nodes: Some(3), ..Default::default()});
+        assert!(!res.complete);
+        assert_eq!(res.num_explored, 3);
+        assert_eq!(res.best_score, None);
+        assert!(res.best_path().is_empty());
+    }
+}
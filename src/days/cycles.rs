@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::{Add, Sub, Mul};
+
+/// The state after `start + length` steps is the same as after `start` steps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: usize,
+    pub length: usize,
+}
+
+impl Cycle {
+    /// Step within the first cycle (or before it) whose state is the same as after `n` steps
+    pub fn equivalent_step(&self, n: usize) -> usize
+    {
+        if n < self.start {
+            n
+        }
+        else {
+            self.start + (n - self.start) % self.length
+        }
+    }
+
+    /// Value after `n` steps of a metric growing by the same amount every cycle (a height, a counter...),
+    /// `history[i]` being its value after i steps. `history` must go at least up to the end of the first cycle.
+    pub fn extrapolate<M>(&self, history: &[M], n: usize) -> M
+    where M: Copy + Add<Output = M> + Sub<Output = M> + Mul<Output = M> + TryFrom<usize>,
+    <M as TryFrom<usize>>::Error: Debug
+    {
+        if n < history.len() {
+            return history[n];
+        }
+        let per_cycle = history[self.start + self.length] - history[self.start];
+        let num_cycles = M::try_from((n - self.start) / self.length).expect("Too many cycles for the metric type");
+        history[self.equivalent_step(n)] + num_cycles * per_cycle
+    }
+}
+
+/// Incremental cycle detection, fed with a fingerprint of the state after every step.
+/// When the fingerprint does not capture the whole state, a repetition can be a coincidence:
+/// a cycle is then only reported once the fingerprints have kept repeating for `confirm_periods` more periods.
+#[derive(Debug, Clone)]
+pub struct CycleDetector<K> {
+    last_seen: HashMap<K, usize>,
+    history: Vec<K>,
+    confirm_periods: usize,
+    candidate: Option<Cycle>,
+}
+
+impl<K> CycleDetector<K>
+where K: Clone + Eq + Hash
+{
+    pub fn new(confirm_periods: usize) -> Self
+    {
+        Self {
+            last_seen: HashMap::new(),
+            history: Vec::new(),
+            confirm_periods,
+            candidate: None,
+        }
+    }
+
+    /// Number of fingerprints pushed so far
+    pub fn num_steps(&self) -> usize
+    {
+        self.history.len()
+    }
+
+    /// Record the fingerprint of the next state, returns the cycle once it is found (and confirmed)
+    pub fn push(&mut self, fingerprint: K) -> Option<Cycle>
+    {
+        let step = self.history.len();
+        if let Some(cycle) = self.candidate {
+            if self.history[step - cycle.length] != fingerprint {
+                self.candidate = None;
+            }
+        }
+        if self.candidate.is_none() {
+            if let Some(&previous) = self.last_seen.get(&fingerprint) {
+                self.candidate = Some(Cycle {start: previous, length: step - previous});
+            }
+        }
+        self.last_seen.insert(fingerprint.clone(), step);
+        self.history.push(fingerprint);
+
+        let confirmed_at = |cycle: &Cycle| cycle.start + cycle.length * (self.confirm_periods + 1);
+        self.candidate.filter(|cycle| step >= confirmed_at(cycle))
+    }
+}
+
+/// Apply `step` to `state` until the fingerprints of the successive states repeat (see `CycleDetector`),
+/// for at most `max_steps` steps. The state is left where the cycle was found.
+pub fn find_cycle<St, K, Step, Fingerprint>(
+    state: &mut St,
+    mut step: Step,
+    mut fingerprint: Fingerprint,
+    confirm_periods: usize,
+    max_steps: usize) -> Option<Cycle>
+where K: Clone + Eq + Hash,
+Step: FnMut(&mut St),
+Fingerprint: FnMut(&St) -> K
+{
+    let mut detector = CycleDetector::new(confirm_periods);
+    detector.push(fingerprint(state));
+    for _ in 0..max_steps {
+        step(state);
+        if let Some(cycle) = detector.push(fingerprint(state)) {
+            return Some(cycle);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_find_cycle() {
+        // 0 1 2 3 4 5 3 4 5 3...
+        let next = |x: &mut u32| *x = if *x == 5 { 3 } else { *x + 1 };
+        let mut x = 0;
+        let cycle = find_cycle(&mut x, next, |x| *x, 0, 100);
+        assert_eq!(cycle, Some(Cycle {start: 3, length: 3}));
+        assert_eq!(x, 3);
+
+        let cycle = cycle.unwrap();
+        assert_eq!(cycle.equivalent_step(2), 2);
+        assert_eq!(cycle.equivalent_step(7), 4);
+        assert_eq!(cycle.equivalent_step(3000), 3);
+
+        let mut x = 0;
+        assert_eq!(find_cycle(&mut x, next, |x| *x, 2, 100), Some(Cycle {start: 3, length: 3}));
+        assert_eq!(x, 3);
+        assert_eq!(find_cycle(&mut 0, next, |x| *x, 2, 10), None);
+    }
+
+    #[test]
+    fn test_false_positive() {
+        // the fingerprint (x % 10) repeats once before the real cycle
+        let sequence = [1, 11, 2, 3, 4, 2, 3, 4];
+        let mut detector = CycleDetector::new(0);
+        let found: Vec<_> = sequence.iter().map(|x| detector.push(x % 10)).collect();
+        assert_eq!(found[1], Some(Cycle {start: 0, length: 1}));
+
+        let mut detector = CycleDetector::new(1);
+        let found: Vec<_> = sequence.iter().map(|x| detector.push(x % 10)).collect();
+        assert!(found.iter().all(|f| f.is_none()));
+        assert_eq!(detector.push(2), Some(Cycle {start: 2, length: 3}));
+        assert_eq!(detector.num_steps(), 9);
+    }
+
+    #[test]
+    fn test_extrapolate() {
+        // grows by 1 before the cycle, then 2, 0, 1 in loop
+        let history: Vec<u64> = vec![0, 1, 2, 4, 4, 5, 7, 7, 8];
+        let cycle = Cycle {start: 2, length: 3};
+        assert_eq!(cycle.extrapolate(&history, 1), 1);
+        assert_eq!(cycle.extrapolate(&history, 8), 8);
+        assert_eq!(cycle.extrapolate(&history, 9), 10);
+        assert_eq!(cycle.extrapolate(&history, 10), 10);
+        assert_eq!(cycle.extrapolate(&history, 11), 11);
+        assert_eq!(cycle.extrapolate(&history, 2 + 3 * 1_000_000), 2 + 3 * 1_000_000);
+    }
+}
@@ -1,149 +1,305 @@
 use crate::days::internal_common::*;
-use std::collections::HashSet;
+use crate::days::ocr;
+use std::fmt;
 
-struct Vm {
-    x: i32,
-    cycle: i32
+pub fn day_10_part_1<Input>(input: &mut Input) -> Result<()>
+where Input: Read
+{
+    let content = get_whole_input_as_string(input)?;
+    let mut cpu = Cpu::new(parse::parse_program(&content, &INSTRUCTION_SET)?);
+
+    for cycle in [20, 60, 100, 140, 180, 220] {
+        cpu.add_breakpoint(Breakpoint::Cycle(cycle));
+    }
+    let mut sum = 0;
+    while let Some(state) = cpu.run_until_break(trace_cycle) {
+        sum += state.cycle as i32 * state.during.x;
+    }
+
+    println!("Sum is {}", sum);
+
+    Ok(())
 }
 
-impl Vm {
-    fn new() -> Self {
-        Self {
-            x: 1,
-            cycle: 1
+pub fn day_10_part_2<Input>(input: &mut Input) -> Result<()>
+where Input: Read
+{
+    let content = get_whole_input_as_string(input)?;
+    let cpu = Cpu::new(parse::parse_program(&content, &INSTRUCTION_SET)?);
+
+    let mut screen: Vec<String> = Vec::new();
+    for state in cpu {
+        trace_cycle(&state);
+        // the pixel drawn during cycle N is the (N - 1)th one
+        let pos_in_line = (state.cycle - 1) % 40;
+        if pos_in_line == 0 {
+            screen.push(String::with_capacity(40));
         }
+        let is_lit = (state.during.x - pos_in_line as i32).abs() <= 1;
+        screen.last_mut().unwrap().push(if is_lit { '#' } else { '.' });
     }
 
-    fn increment_cycle_and_maybe_measure(&mut self, sum: &mut i32, cycles_when_we_measure: &HashSet<i32>) {
-        self.cycle += 1;
-        if cycles_when_we_measure.contains(&self.cycle) {
-            *sum += self.x * self.cycle;
+    if is_verbose() {
+        for line in &screen {
+            println!("{}", line);
         }
     }
+    // the last cycles may draw the start of an extra line
+    screen.truncate(ocr::GLYPH_HEIGHT);
+    println!("Letters are {}", ocr::read_letters(&screen)?);
 
-    fn get_pixel(&self, pos_in_line: i32) -> char {
-        if (self.x - pos_in_line).abs() <= 1 {
-            '#'
-        }
-        else {
-            '.'
-        }
+    Ok(())
+}
+
+fn trace_cycle(state: &CycleState)
+{
+    if is_tracing() {
+        println!("{}", state);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Registers {
+    pub x: i32,
+}
+
+impl Default for Registers {
+    fn default() -> Self
+    {
+        Self { x: 1 }
     }
+}
 
-    fn increment_cycle_and_maybe_draw(&mut self, line_buffer: &mut Vec<char>) {
-        let pos_in_line = self.cycle % 40;
-        line_buffer[pos_in_line as usize] = self.get_pixel(pos_in_line);
-        if pos_in_line == 39 {
-            println!("{}", line_buffer.iter().collect::<String>());
-        }
-        self.cycle += 1;
+/// The effect is applied at the end of the last cycle of the instruction
+#[derive(Clone, Copy)]
+pub struct OpcodeDef {
+    pub name: &'static str,
+    pub num_args: usize,
+    pub num_cycles: usize,
+    pub effect: fn(&mut Registers, &[i32]),
+}
+
+impl fmt::Debug for OpcodeDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
     }
 }
 
-pub fn day_10_part_1<Input>(input: &mut Input) -> Result<()>
-where Input: Read
-{
-    let content = get_whole_input_as_string(input)?;
+pub const INSTRUCTION_SET: [OpcodeDef; 2] = [
+    OpcodeDef { name: "noop", num_args: 0, num_cycles: 1, effect: |_, _| () },
+    OpcodeDef { name: "addx", num_args: 1, num_cycles: 2, effect: |regs, args| regs.x += args[0] },
+];
 
-    let mut vm = Vm::new();
-    let mut sum = 0;
-    let cycles_when_we_measure: HashSet<i32> = HashSet::from(
-        [20, 60, 100, 140, 180, 220]
-    );
-
-    parse::parse_and_do_for_each_line(content.as_str(), |instruction| {
-        
-        match instruction {
-            Instruction::Addx(val) => {
-                vm.increment_cycle_and_maybe_measure(&mut sum, &cycles_when_we_measure);
-                vm.x += val;
-                vm.increment_cycle_and_maybe_measure(&mut sum, &cycles_when_we_measure);
-            },
-            Instruction::Noop => {
-                vm.increment_cycle_and_maybe_measure(&mut sum, &cycles_when_we_measure);
-            }
-        };
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    pub opcode: OpcodeDef,
+    pub args: Vec<i32>,
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.opcode.name)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
         Ok(())
-    })?;
+    }
+}
 
-    println!("Sum is {}", sum);
+/// Registers during and after a cycle, cycles start at 1
+#[derive(Debug, Clone)]
+pub struct CycleState {
+    pub cycle: usize,
+    pub instruction: Instruction,
+    /// Cycle of the current instruction, from 1 to its number of cycles
+    pub instruction_cycle: usize,
+    pub during: Registers,
+    pub after: Registers,
+}
 
-    Ok(())
+impl fmt::Display for CycleState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let instruction = format!("{} ({}/{})", self.instruction, self.instruction_cycle, self.instruction.opcode.num_cycles);
+        write!(f, "cycle {:>4} | {:<16} | x during {:>4}, after {:>4}", self.cycle, instruction, self.during.x, self.after.x)
+    }
 }
 
-pub fn day_10_part_2<Input>(input: &mut Input) -> Result<()>
-where Input: Read
-{
-    let content = get_whole_input_as_string(input)?;
+pub enum Breakpoint {
+    Cycle(usize),
+    When(Box<dyn Fn(&CycleState) -> bool>),
+}
 
-    let mut vm = Vm::new();
-    let mut line_buffer: Vec<char> = vec!['.'; 40];
-
-    parse::parse_and_do_for_each_line(content.as_str(), |instruction| {
-        
-        match instruction {
-            Instruction::Addx(val) => {
-                vm.increment_cycle_and_maybe_draw(&mut line_buffer);
-                vm.x += val;
-                vm.increment_cycle_and_maybe_draw(&mut line_buffer);
-            },
-            Instruction::Noop => {
-                vm.increment_cycle_and_maybe_draw(&mut line_buffer);
-            }
-        };
+impl Breakpoint {
+    fn is_hit(&self, state: &CycleState) -> bool
+    {
+        match self {
+            Self::Cycle(cycle) => state.cycle == *cycle,
+            Self::When(predicate) => predicate(state),
+        }
+    }
+}
 
-        Ok(())
-    })?;
+pub struct Cpu {
+    program: Vec<Instruction>,
+    registers: Registers,
+    // next instruction
+    pc: usize,
+    // cycles already spent on it
+    instruction_cycle: usize,
+    cycle: usize,
+    breakpoints: Vec<Breakpoint>,
+}
 
-    Ok(())
+impl Cpu {
+    pub fn new(program: Vec<Instruction>) -> Self
+    {
+        Self {
+            program,
+            registers: Registers::default(),
+            pc: 0,
+            instruction_cycle: 0,
+            cycle: 0,
+            breakpoints: Vec::new(),
+        }
+    }
+
+    pub fn registers(&self) -> Registers
+    {
+        self.registers
+    }
+
+    pub fn is_halted(&self) -> bool
+    {
+        self.pc >= self.program.len()
+    }
+
+    /// None once the program is over
+    pub fn step_cycle(&mut self) -> Option<CycleState>
+    {
+        let instruction = self.program.get(self.pc)?.clone();
+        self.cycle += 1;
+        self.instruction_cycle += 1;
+        let during = self.registers;
+        let instruction_cycle = self.instruction_cycle;
+
+        if self.instruction_cycle == instruction.opcode.num_cycles {
+            (instruction.opcode.effect)(&mut self.registers, &instruction.args);
+            self.pc += 1;
+            self.instruction_cycle = 0;
+        }
+        Some(CycleState {
+            cycle: self.cycle,
+            instruction,
+            instruction_cycle,
+            during,
+            after: self.registers,
+        })
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint)
+    {
+        self.breakpoints.push(breakpoint);
+    }
+
+    /// Step until a cycle hits a breakpoint and return it, None if the program ends first.
+    /// `observer` sees every cycle, including the returned one.
+    pub fn run_until_break<F>(&mut self, mut observer: F) -> Option<CycleState>
+    where F: FnMut(&CycleState)
+    {
+        while let Some(state) = self.step_cycle() {
+            observer(&state);
+            if self.breakpoints.iter().any(|breakpoint| breakpoint.is_hit(&state)) {
+                return Some(state);
+            }
+        }
+        None
+    }
 }
 
-#[derive(Debug)]
-enum Instruction {
-    Addx(i32),
-    Noop
+impl Iterator for Cpu {
+    type Item = CycleState;
+
+    fn next(&mut self) -> Option<CycleState>
+    {
+        self.step_cycle()
+    }
 }
 
 mod parse {
     use crate::days::parse::*;
+    use super::{Instruction, OpcodeDef};
     use nom::{
-        character::complete::newline,
-        sequence::{preceded, terminated},
+        character::complete::{alpha1, newline, space1},
+        sequence::{preceded, terminated, pair},
         combinator::{opt, map_res},
-        branch::alt,
-        bytes::complete::tag
+        multi::many0,
+        error::{context, VerboseError}
     };
 
-    pub(super) fn parse_and_do_for_each_line<F>(input: &str, mut func: F) -> super::Result<()>
-    where F: FnMut(super::Instruction) -> super::Result<()>
+    pub(super) fn parse_program(input: &str, instruction_set: &[OpcodeDef]) -> super::Result<Vec<Instruction>>
     {
+        let mut program = Vec::new();
         let mut i = input;
-        while i.len() != 0 {
-
-            let parse_addx = map_res(
-                preceded(tag("addx "), parse_int),
-                |add_arg| Ok::<_, ()>(super::Instruction::Addx(add_arg))
-            );
-
-            let parse_noop = map_res(
-                tag("noop"),
-                |_| Ok::<_, ()>(super::Instruction::Noop)
-            );
-
-            let res = terminated(
-                alt((
-                    parse_addx,
-                    parse_noop
-                )),
-                opt(newline)
-            )(i);
+        while !i.is_empty() {
+
+            let parse_instruction = context("instruction", map_res(
+                pair(alpha1, many0(preceded(space1, parse_int::<i32, VerboseError<&str>>))),
+                |(name, args)| {
+                    let opcode = instruction_set.iter().find(|opcode| opcode.name == name).ok_or(())?;
+                    if args.len() != opcode.num_args {
+                        return Err(());
+                    }
+                    Ok(Instruction { opcode: *opcode, args })
+                }
+            ));
+
+            let res = terminated(parse_instruction, opt(newline))(i);
 
             let (new_i, instruction) = make_verbose_error_message(input, res)?;
 
-            func(instruction)?;
+            program.push(instruction);
             i = new_i;
         }
 
-        Ok(())
+        Ok(program)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cycles() {
+        let program = parse::parse_program("noop\naddx 3\naddx -5\n", &INSTRUCTION_SET).unwrap();
+        let mut cpu = Cpu::new(program);
+        let states: Vec<CycleState> = cpu.by_ref().collect();
+        assert!(cpu.is_halted());
+        assert_eq!(cpu.registers().x, -1);
+
+        let during: Vec<i32> = states.iter().map(|state| state.during.x).collect();
+        let after: Vec<i32> = states.iter().map(|state| state.after.x).collect();
+        assert_eq!(during, [1, 1, 1, 4, 4]);
+        assert_eq!(after, [1, 1, 4, 4, -1]);
+        assert_eq!(states[2].to_string(), "cycle    3 | addx 3 (2/2)     | x during    1, after    4");
+
+        assert!(parse::parse_program("addx\n", &INSTRUCTION_SET).is_err());
+        assert!(parse::parse_program("mulx 3\n", &INSTRUCTION_SET).is_err());
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let program = parse::parse_program("addx 2\naddx 3\nnoop\naddx -1\n", &INSTRUCTION_SET).unwrap();
+        let mut cpu = Cpu::new(program);
+        cpu.add_breakpoint(Breakpoint::Cycle(2));
+        cpu.add_breakpoint(Breakpoint::When(Box::new(|state| state.during.x != state.after.x && state.after.x == 6)));
+
+        let mut num_observed = 0;
+        let state = cpu.run_until_break(|_| num_observed += 1).unwrap();
+        assert_eq!((state.cycle, state.after.x, num_observed), (2, 3, 2));
+        let state = cpu.run_until_break(|_| num_observed += 1).unwrap();
+        assert_eq!((state.cycle, state.instruction.to_string()), (4, String::from("addx 3")));
+        assert!(cpu.run_until_break(|_| num_observed += 1).is_none());
+        assert_eq!(num_observed, 7);
     }
 }
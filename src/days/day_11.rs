@@ -1,111 +1,263 @@
 use crate::days::internal_common::*;
+use crate::days::cycles;
+use crate::days::top_k::TopK;
+use num_bigint::BigInt;
+use std::hash::Hash;
 
 pub fn day_11_part_1<Input>(input: &mut Input) -> Result<()>
 where Input: Read
 {
     let content = get_whole_input_as_string(input)?;
+    let monkeys = parse_monkeys(&content)?;
 
-    let mut monkeys: Vec<Monkey> = Vec::new();
-    
-    parse::parse_and_do_for_each_monkey(content.as_str(), |monkey| {
-        monkeys.push(monkey);
-        Ok(())
-    })?;
-
-    let mut num_inspections: Vec<usize> = vec![0; monkeys.len()];
-
-    for _ in 0..20 {
-        do_round(&mut monkeys, &mut num_inspections, |x| x / 3);
-    }
+    // with the relief, levels stay small enough to be computed exactly
+    let num_inspections = count_inspections_with_mode(&monkeys, 20, true, WorryMode::Exact)?;
 
-    num_inspections.sort();
-    
-    let ans = num_inspections[num_inspections.len() - 1] * num_inspections[num_inspections.len() - 2];
+    let mut most_active: TopK<usize> = TopK::new(2);
+    most_active.extend(num_inspections);
+    let ans: usize = most_active.iter().product();
     println!("Ans {}", ans);
 
     Ok(())
 }
 
-fn do_round<F>(monkeys: &mut Vec<Monkey>, num_inspections: &mut Vec<usize>, reduce_level_func: F)
-where F: Fn(usize) -> usize
-{
-    for monkey_idx in 0..monkeys.len() {
-        for item_idx in 0..monkeys[monkey_idx].items.len() {
-            let old_level = monkeys[monkey_idx].items[item_idx];
-            let new_level = match monkeys[monkey_idx].op {
-                Operation::Add(x) => old_level + x,
-                Operation::Mul(x) => old_level * x,
-                Operation::Square => old_level * old_level,
-            };
-            let new_level = reduce_level_func(new_level);
-            let test = &monkeys[monkey_idx].test;
-            let target = match new_level % test.divisor {
-                0 => test.target_if_true,
-                _ => test.target_if_false,
-            };
-            monkeys[target].items.push(new_level);
-        }
-        num_inspections[monkey_idx] += monkeys[monkey_idx].items.len();
-        monkeys[monkey_idx].items.clear();
-    }
-}
-
 pub fn day_11_part_2<Input>(input: &mut Input) -> Result<()>
 where Input: Read
 {
     let content = get_whole_input_as_string(input)?;
+    let monkeys = parse_monkeys(&content)?;
+
+    let num_inspections = count_inspections_with_mode(&monkeys, 10000, false, WorryMode::Modular)?;
+
+    let mut most_active: TopK<usize> = TopK::new(2);
+    most_active.extend(num_inspections);
+    let ans: usize = most_active.iter().product();
+    println!("Ans {}", ans);
+
+    Ok(())
+}
 
+fn parse_monkeys(content: &str) -> Result<Vec<Monkey>>
+{
     let mut monkeys: Vec<Monkey> = Vec::new();
-    
-    parse::parse_and_do_for_each_monkey(content.as_str(), |monkey| {
+    parse::parse_and_do_for_each_monkey(content, |monkey| {
         monkeys.push(monkey);
         Ok(())
     })?;
+    Ok(monkeys)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WorryMode {
+    /// Levels modulo the LCM of the divisors, only compatible with + - and *
+    Modular,
+    /// Levels as big integers, their size is not bounded without the relief
+    Exact,
+}
+
+fn count_inspections_with_mode(monkeys: &[Monkey], num_rounds: usize, relief: bool, mode: WorryMode) -> Result<Vec<usize>>
+{
+    match mode {
+        WorryMode::Modular => {
+            if relief || monkeys.iter().any(|monkey| monkey.op.has_division()) {
+                return Err(Error::Unsupported(String::from("division with modular worry levels")));
+            }
+            // The idea is to decrease x while keeping the same modulo for all divisors d
+            // We use the fact that x = x - n*m (mod d) for any n, m being a multiple of every d
+            // The smallest such m is the LCM of the divisors
+            let modulus = monkeys.iter().fold(1, |acc, monkey| lcm(acc, monkey.test.divisor as usize)) as u64;
+            Ok(count_inspections(monkeys, num_rounds, relief, |level| Modular::new(level, modulus)))
+        },
+        WorryMode::Exact => Ok(count_inspections(monkeys, num_rounds, relief, BigInt::from)),
+    }
+}
+
+/// Worry level of an item
+trait Worry: Clone + Eq + Hash {
+    /// Literal of an operation, in the same representation as `self`
+    fn literal(&self, value: u64) -> Self;
+    fn apply(&self, op: BinOp, rhs: &Self) -> Self;
+    fn is_divisible_by(&self, divisor: u64) -> bool;
+}
+
+impl Worry for BigInt {
+    fn literal(&self, value: u64) -> Self
+    {
+        BigInt::from(value)
+    }
+
+    fn apply(&self, op: BinOp, rhs: &Self) -> Self
+    {
+        match op {
+            BinOp::Add => self + rhs,
+            BinOp::Sub => self - rhs,
+            BinOp::Mul => self * rhs,
+            BinOp::Div => self / rhs,
+        }
+    }
+
+    fn is_divisible_by(&self, divisor: u64) -> bool
+    {
+        (self % divisor) == BigInt::from(0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Modular {
+    value: u64,
+    modulus: u64,
+}
+
+impl Modular {
+    fn new(value: u64, modulus: u64) -> Self
+    {
+        Self { value: value % modulus, modulus }
+    }
+}
+
+impl Worry for Modular {
+    fn literal(&self, value: u64) -> Self
+    {
+        Self::new(value, self.modulus)
+    }
+
+    fn apply(&self, op: BinOp, rhs: &Self) -> Self
+    {
+        let (a, b, m) = (self.value as u128, rhs.value as u128, self.modulus as u128);
+        let value = match op {
+            BinOp::Add => (a + b) % m,
+            BinOp::Sub => (a + m - b) % m,
+            BinOp::Mul => (a * b) % m,
+            BinOp::Div => panic!("Division does not preserve the remainders"),
+        };
+        Self { value: value as u64, modulus: self.modulus }
+    }
+
+    fn is_divisible_by(&self, divisor: u64) -> bool
+    {
+        self.value.is_multiple_of(divisor)
+    }
+}
 
+// Items never interact, so each one is followed on its own through the rounds.
+// Its trajectory ends up looping as soon as its worry level is bounded, which allows to skip most rounds.
+fn count_inspections<W, F>(monkeys: &[Monkey], num_rounds: usize, relief: bool, to_worry: F) -> Vec<usize>
+where W: Worry,
+F: Fn(u64) -> W
+{
     let mut num_inspections: Vec<usize> = vec![0; monkeys.len()];
+    for (monkey_idx, monkey) in monkeys.iter().enumerate() {
+        for &level in &monkey.items {
+            let mut item = ItemState { monkey_idx, level: to_worry(level) };
+            // history[round][monkey]: inspections of the item by the monkey after that many rounds
+            let mut history = vec![vec![0; monkeys.len()]];
+            let cycle = cycles::find_cycle(&mut item,
+                |item| {
+                    let mut inspections = history.last().unwrap().clone();
+                    do_item_round(monkeys, item, &mut inspections, relief);
+                    history.push(inspections);
+                },
+                |item| item.clone(),
+                0,
+                num_rounds
+            );
+            for (target_idx, count) in num_inspections.iter_mut().enumerate() {
+                let monkey_history: Vec<usize> = history.iter().map(|inspections| inspections[target_idx]).collect();
+                *count += match cycle {
+                    Some(cycle) => cycle.extrapolate(&monkey_history, num_rounds),
+                    None => monkey_history[num_rounds],
+                };
+            }
+        }
+    }
+    num_inspections
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct ItemState<W> {
+    monkey_idx: usize,
+    level: W,
+}
 
-    // The idea is to decrease x while keeping the same modulo for all divisors d
-    // We use the fact that x = x - n*d (mod d) for any n
-    // So x = x - d0*d1...*dn preserves the modulo for all d
-    // Doing a "% n" operation is like subtracting n multiple times, so we can
-    // do x = x % d0*d1...*dn
-    let magic_value = monkeys.iter().fold(1, |acc, monkey| acc * monkey.test.divisor);
-    for _ in 0..10000 {
-        do_round(&mut monkeys, &mut num_inspections, |x| x % magic_value);
+// monkeys play in order, so the item is inspected again in the same round when thrown to a later monkey
+fn do_item_round<W>(monkeys: &[Monkey], item: &mut ItemState<W>, num_inspections: &mut [usize], relief: bool)
+where W: Worry
+{
+    loop {
+        let monkey = &monkeys[item.monkey_idx];
+        num_inspections[item.monkey_idx] += 1;
+        let mut new_level = monkey.op.eval(&item.level);
+        if relief {
+            new_level = new_level.apply(BinOp::Div, &new_level.literal(3));
+        }
+        let test = &monkey.test;
+        let target = match new_level.is_divisible_by(test.divisor) {
+            true => test.target_if_true,
+            false => test.target_if_false,
+        };
+        let thrown_backward = target < item.monkey_idx;
+        *item = ItemState { monkey_idx: target, level: new_level };
+        if thrown_backward {
+            break;
+        }
     }
+}
 
-    num_inspections.sort();
-    
-    let ans = num_inspections[num_inspections.len() - 1] * num_inspections[num_inspections.len() - 2];
-    println!("Ans {}", ans);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
 
-    Ok(())
+/// Right hand side of "new = ..."
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Old,
+    Literal(u64),
+    BinOp(Box<Expr>, BinOp, Box<Expr>),
 }
 
-#[derive(Debug)]
-enum Operation {
-    Add(usize),
-    Mul(usize),
-    Square
+impl Expr {
+    fn eval<W>(&self, old: &W) -> W
+    where W: Worry
+    {
+        match self {
+            Self::Old => old.clone(),
+            Self::Literal(value) => old.literal(*value),
+            Self::BinOp(lhs, op, rhs) => lhs.eval(old).apply(*op, &rhs.eval(old)),
+        }
+    }
+
+    fn has_division(&self) -> bool
+    {
+        match self {
+            Self::BinOp(lhs, op, rhs) => *op == BinOp::Div || lhs.has_division() || rhs.has_division(),
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, Default)]
 struct Test {
-    divisor: usize,
+    divisor: u64,
     target_if_true: usize,
     target_if_false: usize
 }
 
 #[derive(Debug)]
 struct Monkey {
-    items: Vec<usize>,
-    op: Operation,
+    items: Vec<u64>,
+    op: Expr,
     test: Test
 }
 
 mod parse {
     use crate::days::parse::*;
     use crate::days::parse::nom_goes_brrr::*;
+    use nom::{character::complete::space0, combinator::map};
+    use super::{Expr, BinOp};
 
     pub(super) fn parse_and_do_for_each_monkey<F>(input: &str, mut func: F) -> super::Result<()>
     where F: FnMut(super::Monkey) -> super::Result<()>
@@ -113,28 +265,12 @@ mod parse {
         let mut i = input;
         while i.starts_with("Monkey") {
 
-            let parse_int_list = many0(terminated(parse_int::<usize, _>, opt(tag(", "))));
-            let parse_operation_args = context("operation_args",
-                alt((
-                    map_res(
-                        separated_pair(anychar, tag(" "), parse_int::<usize, _>),
-                        |(c, i)| match c {
-                            '+' => Ok(super::Operation::Add(i)),
-                            '*' => Ok(super::Operation::Mul(i)),
-                            _ => Err(())
-                        }),
-                    map_res(
-                        tag("* old"),
-                        |_| Ok::<_, ()>(super::Operation::Square)
-                    )
-                ))
-            );
-
+            let parse_int_list = many0(terminated(parse_int::<u64, _>, opt(tag(", "))));
             let mut parse_monkey_idx = delimited(tag("Monkey "), parse_int::<usize, _>, tag(":\n"));
             let mut parse_starting_items = delimited(tag("  Starting items: "), parse_int_list, newline);
-            let mut parse_operation = delimited(tag("  Operation: new = old "), parse_operation_args, newline);
+            let mut parse_operation = delimited(tag("  Operation: new = "), context("operation", parse_expr), newline);
             let parse_test = tuple((
-                delimited(tag("  Test: divisible by "), parse_int::<usize, _>, newline),
+                delimited(tag("  Test: divisible by "), parse_int::<u64, _>, newline),
                 delimited(tag("    If true: throw to monkey "), parse_int::<usize, _>, newline),
                 delimited(tag("    If false: throw to monkey "), parse_int::<usize, _>, newline),
             ));
@@ -159,4 +295,108 @@ mod parse {
         }
         Ok(())
     }
-}
\ No newline at end of file
+
+    // sums of products, products binding tighter
+    pub(super) fn parse_expr<'a, E>(i: &'a str) -> IResult<&'a str, Expr, E>
+    where E: ParseError<&'a str>
+    {
+        parse_bin_ops(i, &[('+', BinOp::Add), ('-', BinOp::Sub)], parse_term)
+    }
+
+    fn parse_term<'a, E>(i: &'a str) -> IResult<&'a str, Expr, E>
+    where E: ParseError<&'a str>
+    {
+        parse_bin_ops(i, &[('*', BinOp::Mul), ('/', BinOp::Div)], parse_atom)
+    }
+
+    fn parse_atom<'a, E>(i: &'a str) -> IResult<&'a str, Expr, E>
+    where E: ParseError<&'a str>
+    {
+        alt((
+            map(tag("old"), |_| Expr::Old),
+            map(parse_int::<u64, E>, Expr::Literal),
+            delimited(tag("("), parse_expr, tag(")")),
+        ))(i)
+    }
+
+    // left associative chain of operands separated by one of `ops`
+    fn parse_bin_ops<'a, E, F>(i: &'a str, ops: &[(char, BinOp)], mut parse_operand: F) -> IResult<&'a str, Expr, E>
+    where E: ParseError<&'a str>,
+    F: FnMut(&'a str) -> IResult<&'a str, Expr, E>
+    {
+        let (mut i, mut expr) = parse_operand(i)?;
+        loop {
+            let op = delimited(space0::<&str, E>, anychar, space0)(i).ok()
+                .and_then(|(next_i, c)| ops.iter().find(|(op_char, _)| *op_char == c).map(|(_, op)| (next_i, *op)));
+            let Some((next_i, op)) = op else {
+                return Ok((i, expr));
+            };
+            let (next_i, rhs) = parse_operand(next_i)?;
+            expr = Expr::BinOp(Box::new(expr), op, Box::new(rhs));
+            i = next_i;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+Monkey 0:
+  Starting items: 79, 98
+  Operation: new = old * 19
+  Test: divisible by 23
+    If true: throw to monkey 2
+    If false: throw to monkey 3
+
+Monkey 1:
+  Starting items: 54, 65, 75, 74
+  Operation: new = old + 6
+  Test: divisible by 19
+    If true: throw to monkey 2
+    If false: throw to monkey 0
+
+Monkey 2:
+  Starting items: 79, 60, 97
+  Operation: new = old * old
+  Test: divisible by 13
+    If true: throw to monkey 1
+    If false: throw to monkey 3
+
+Monkey 3:
+  Starting items: 74
+  Operation: new = old + 3
+  Test: divisible by 17
+    If true: throw to monkey 0
+    If false: throw to monkey 1
+";
+
+    #[test]
+    fn test_worry_modes() {
+        let monkeys = parse_monkeys(EXAMPLE).unwrap();
+        assert_eq!(count_inspections_with_mode(&monkeys, 20, true, WorryMode::Exact).unwrap(), [101, 95, 7, 105]);
+        assert!(count_inspections_with_mode(&monkeys, 20, true, WorryMode::Modular).is_err());
+
+        let exact = count_inspections_with_mode(&monkeys, 20, false, WorryMode::Exact).unwrap();
+        assert_eq!(exact, [99, 97, 8, 103]);
+        assert_eq!(count_inspections_with_mode(&monkeys, 20, false, WorryMode::Modular).unwrap(), exact);
+        assert_eq!(count_inspections_with_mode(&monkeys, 10000, false, WorryMode::Modular).unwrap(), [52166, 47830, 1938, 52013]);
+    }
+
+    #[test]
+    fn test_expressions() {
+        fn parse(s: &str) -> (&str, Expr) {
+            parse::parse_expr::<nom::error::Error<&str>>(s).unwrap()
+        }
+        let (rem, expr) = parse("3 * (old - 1) + old / 2\n");
+        assert_eq!(rem, "\n");
+        assert_eq!(expr.eval(&BigInt::from(10)), BigInt::from(32));
+        assert!(expr.has_division());
+
+        let (_, expr) = parse("old - 7 - old");
+        assert_eq!(expr.eval(&BigInt::from(5)), BigInt::from(-7));
+        assert_eq!(expr.eval(&Modular::new(5, 11)), Modular::new(4, 11));
+        assert!(!expr.has_division());
+    }
+}
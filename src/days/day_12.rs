@@ -1,5 +1,9 @@
 use crate::days::internal_common::*;
 use macro_lib::Grid2D;
+use crate::days::search;
+use crate::days::directions::Direction4 as Direction;
+
+type Point = crate::days::points::Point2<i32>;
 
 #[derive(Debug, Grid2D)]
 struct Grid {
@@ -40,7 +44,7 @@ impl Grid {
             line_idx += 1;
             for c in line.bytes() {
                 let elevation;
-                if (b'a'..=b'z').contains(&c) {
+                if c.is_ascii_lowercase() {
                     elevation = c;
                 }
                 else if c == b'S' {
@@ -72,46 +76,78 @@ impl Grid {
 
 }
 
-fn compute_cost_recurs(current: &Node, grid: &Grid, costs: &mut Vec<usize>)
-{
-    let node_accessible_from_current = |node: &Node, current: &Node| {
-        current.data <= node.data + 1
-    };
-
-    let mut next_candidates: Vec<Node> = Vec::with_capacity(4);
-    if let Some(node) = grid.get_node_left(current) {
-        if node_accessible_from_current(&node, current) {
-            next_candidates.push(node);
-        }
-    }
-    if let Some(node) = grid.get_node_right(current) {
-        if node_accessible_from_current(&node, current) {
-            next_candidates.push(node);
-        }
-    }
-    if let Some(node) = grid.get_node_up(current) {
-        if node_accessible_from_current(&node, current) {
-            next_candidates.push(node);
+/// Cost of a move to a neighbor at most one level higher
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CostModel {
+    Steps,
+    /// One per step, plus `per_level` for every level climbed
+    Climb { per_level: usize },
+}
+
+impl CostModel {
+    fn move_cost(&self, from_elevation: u8, to_elevation: u8) -> usize
+    {
+        match self {
+            Self::Steps => 1,
+            Self::Climb { per_level } => 1 + per_level * to_elevation.saturating_sub(from_elevation) as usize,
         }
     }
-    if let Some(node) = grid.get_node_down(current) {
-        if node_accessible_from_current(&node, current) {
-            next_candidates.push(node);
-        }
+}
+
+/// Cheapest way from a start to the end
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub cost: usize,
+    /// Node ids, from the start to the end
+    pub path: Vec<usize>,
+}
+
+impl Grid {
+    // nodes from which `current` can be reached, with the cost of that move
+    fn get_reverse_neighbors(&self, current: usize, cost_model: CostModel) -> Vec<(usize, usize)>
+    {
+        let current = self.get_node_from_id(current);
+        self.get_neighbors(&current).into_iter()
+            .filter(|node| current.data <= node.data + 1)
+            .map(|node| (node.id, cost_model.move_cost(node.data, current.data)))
+            .collect()
     }
 
-    if next_candidates.len() == 0 {
-        return;
+    /// Searches backward from the end, so that every node satisfying `is_start` is a possible start
+    fn find_route<F>(&self, cost_model: CostModel, is_start: F) -> Result<Route>
+    where F: Fn(usize) -> bool
+    {
+        let res = search::dijkstra(
+            [self.end.id],
+            |&id| self.get_reverse_neighbors(id, cost_model),
+            |&id| is_start(id)
+        );
+        let start = res.goal.ok_or(Error::NoSolution)?;
+        let mut path = res.path_to(&start).ok_or(Error::NoSolution)?;
+        path.reverse();
+        Ok(Route { cost: res.cost(&start).ok_or(Error::NoSolution)?, path })
     }
 
-    for candidate in next_candidates {
-        let new_cost = costs[current.id] + 1;
-        if new_cost < costs[candidate.id] {
-            costs[candidate.id] = new_cost;
-            compute_cost_recurs(&candidate, grid, costs);
+    /// The map with arrows along the route, as in the puzzle description
+    fn render_route(&self, route: &Route) -> String
+    {
+        let mut chars = vec!['.'; self.data.len()];
+        chars[self.end.id] = 'E';
+        let to_point = |id: usize| Point::from_xy((id % self.width) as i32, (id / self.width) as i32);
+        for step in route.path.windows(2) {
+            let dir = Direction::from_point(&(to_point(step[1]) - to_point(step[0]))).expect("Route between non neighbors");
+            chars[step[0]] = dir.to_arrow();
         }
+        chars.chunks(self.width).map(|row| row.iter().collect::<String>() + "\n").collect()
     }
+}
 
+fn print_route(grid: &Grid, route: &Route)
+{
+    if is_verbose() {
+        print!("{}", grid.render_route(route));
+    }
+    println!("Cost is {}", route.cost);
 }
 
 pub fn day_12_part_1<Input>(input: &mut Input) -> Result<()>
@@ -119,11 +155,8 @@ where Input: Read
 {
     let grid = Grid::from_input(input)?;
 
-    let mut costs: Vec<usize> = vec![usize::MAX; grid.data.len()];
-    costs[grid.end.id] = 0;
-    compute_cost_recurs(&grid.end, &grid, &mut costs);
-
-    println!("Cost is {}", costs[grid.start.id]);
+    let route = grid.find_route(CostModel::Steps, |id| id == grid.start.id)?;
+    print_route(&grid, &route);
 
     Ok(())
 }
@@ -133,14 +166,43 @@ where Input: Read
 {
     let grid = Grid::from_input(input)?;
 
-    let mut costs: Vec<usize> = vec![usize::MAX; grid.data.len()];
-    costs[grid.end.id] = 0;
-    compute_cost_recurs(&grid.end, &grid, &mut costs);
-
-    let costs_with_idx_with_elevation_a: Vec<(usize, usize)> =
-        costs.into_iter().enumerate().filter(|&(node_idx, _)| grid.data[node_idx] == b'a').collect();
-    let min_cost = costs_with_idx_with_elevation_a.iter().min_by(|a, b| a.1.partial_cmp(&b.1).unwrap()).unwrap().1;
-    println!("Cost is {}", min_cost);
+    // the first elevation 'a' reached from the end is the closest one
+    let route = grid.find_route(CostModel::Steps, |id| grid.data[id] == b'a')?;
+    print_route(&grid, &route);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+Sabqponm
+abcryxxl
+accszExk
+acctuvwj
+abdefghi
+";
+
+    #[test]
+    fn test_routes() {
+        let grid = Grid::from_input(&mut EXAMPLE.as_bytes()).unwrap();
+        let route = grid.find_route(CostModel::Steps, |id| id == grid.start.id).unwrap();
+        assert_eq!(route.cost, 31);
+        assert_eq!(route.path.len(), 32);
+        assert_eq!((route.path[0], *route.path.last().unwrap()), (grid.start.id, grid.end.id));
+
+        let drawing = grid.render_route(&route);
+        assert_eq!(drawing.lines().count(), 5);
+        assert_eq!(drawing.chars().filter(|c| "<>^v".contains(*c)).count(), 31);
+        assert!(drawing.starts_with('v') && drawing.contains('E'));
+
+        let closest_a = grid.find_route(CostModel::Steps, |id| grid.data[id] == b'a').unwrap();
+        assert_eq!(closest_a.cost, 29);
+
+        // the shortest route only climbs, one level at a time
+        let climbing = grid.find_route(CostModel::Climb { per_level: 2 }, |id| id == grid.start.id).unwrap();
+        assert_eq!(climbing.cost, 31 + 2 * 25);
+    }
+}
@@ -1,4 +1,7 @@
 use crate::days::internal_common::*;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
 
 pub fn day_13_part_1<Input>(input: &mut Input) -> Result<()>
 where Input: Read
@@ -7,7 +10,7 @@ where Input: Read
     let mut sum = 0;
     let mut idx = 1;
     parse::parse_and_do_for_packet_pair(&input, |a, b| {
-        if a < b {
+        if a.signal_cmp(&b) == Ordering::Less {
             sum += idx;
         }
         idx += 1;
@@ -23,38 +26,91 @@ pub fn day_13_part_2<Input>(input: &mut Input) -> Result<()>
 where Input: Read
 {
     let input = get_whole_input_as_string(input)?;
-    let mut packets = parse::parse_and_collect_packets(&input)?;
-    packets.push(PacketElement::Int(2));
-    packets.push(PacketElement::Int(6));
-
-    let mut indices: Vec<usize> = (0..packets.len()).into_iter().collect();
-    let special_index_0 = packets.len() - 2;
-    let mut special_index_0_new_pos = 0;
-    let special_index_1 = packets.len() - 1;
-    let mut special_index_1_new_pos = 0;
-    indices.sort_by(|&a, &b| packets[a].cmp(&packets[b]));
-    for (new_pos, &index) in indices.iter().enumerate() {
-        if index == special_index_0 {
-            special_index_0_new_pos = new_pos + 1;
-        }
-        else if index == special_index_1 {
-            special_index_1_new_pos = new_pos + 1;
-        }
+    let packets = parse::parse_and_collect_packets(&input)?;
+
+    // no need to sort, the position of a divider is one plus the number of packets before it
+    let dividers: [Packet; 2] = ["[[2]]".parse()?, "[[6]]".parse()?];
+    let mut product = 1;
+    for (i, divider) in dividers.iter().enumerate() {
+        let num_before = packets.iter().filter(|packet| packet.signal_cmp(divider) == Ordering::Less).count();
+        product *= num_before + i + 1;
     }
-    let product = special_index_0_new_pos * special_index_1_new_pos;
     println!("Special product is {}", product);
 
     Ok(())
 }
 
-impl std::fmt::Display for PacketElement {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// A packet, written like a JSON array of non-negative integers
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Packet {
+    Int(u32),
+    List(Vec<Packet>),
+}
+
+impl Packet {
+    /// Order of the puzzle, where an integer compared to a list is a list of one integer.
+    /// `2` and `[[2]]` are equivalent in this order, unlike with `Ord`.
+    pub fn signal_cmp(&self, other: &Self) -> Ordering
+    {
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => a.cmp(b),
+            (Self::List(a), Self::List(b)) => Self::signal_cmp_lists(a, b),
+            (Self::Int(_), Self::List(b)) => Self::signal_cmp_lists(std::slice::from_ref(self), b),
+            (Self::List(a), Self::Int(_)) => Self::signal_cmp_lists(a, std::slice::from_ref(other)),
+        }
+    }
+
+    // lexicographic, a prefix comes first
+    fn signal_cmp_lists(a: &[Self], b: &[Self]) -> Ordering
+    {
+        a.iter().zip(b)
+            .map(|(a, b)| a.signal_cmp(b))
+            .find(|&ord| ord != Ordering::Equal)
+            .unwrap_or_else(|| a.len().cmp(&b.len()))
+    }
+
+    // any total order agreeing with `Eq`, integers first
+    fn structural_cmp(&self, other: &Self) -> Ordering
+    {
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => a.cmp(b),
+            (Self::List(a), Self::List(b)) => a.iter().zip(b)
+                .map(|(a, b)| a.structural_cmp(b))
+                .find(|&ord| ord != Ordering::Equal)
+                .unwrap_or_else(|| a.len().cmp(&b.len())),
+            (Self::Int(_), Self::List(_)) => Ordering::Less,
+            (Self::List(_), Self::Int(_)) => Ordering::Greater,
+        }
+    }
+}
+
+/// The puzzle order, ties between equivalent but different packets being broken structurally
+impl Ord for Packet {
+    fn cmp(&self, other: &Self) -> Ordering
+    {
+        self.signal_cmp(other).then_with(|| self.structural_cmp(other))
+    }
+}
+
+impl PartialOrd for Packet {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering>
+    {
+        Some(self.cmp(other))
+    }
+}
+
+/// Same format as the input, e.g. `[1,[2,3]]`
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Int(x) => write!(f, "{}", x),
-            Self::Array(a) => {
+            Self::List(list) => {
                 write!(f, "[")?;
-                for e in a {
-                    e.fmt(f)?;
+                for (i, packet) in list.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", packet)?;
                 }
                 write!(f, "]")
             }
@@ -62,119 +118,85 @@ impl std::fmt::Display for PacketElement {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum PacketElement {
-    Int(u32),
-    Array(Vec<PacketElement>)
-}
+impl FromStr for Packet {
+    type Err = Error;
 
-impl PacketElement {
-    fn is_int(&self) -> bool {
-        match self {
-            Self::Int(_) => true,
-            _ => false
-        }
+    fn from_str(s: &str) -> Result<Self>
+    {
+        parse::parse_whole_packet(s)
     }
 }
 
-fn cmp_int_array(a: &PacketElement, b: &PacketElement) -> Option<std::cmp::Ordering> {
-
-    use std::cmp::Ordering;
-
-    let (a_inner, b_inner) = match (a, b) {
-        (PacketElement::Int(a), PacketElement::Array(b)) => (a, b),
-        _ => panic!("Expected int and array variants")
-    };
-    
-    if b_inner.len() == 0 {
-        return Some(Ordering::Greater);
-    }
-    else {
-        let ord = match b_inner[0] {
-            PacketElement::Int(x) => a_inner.partial_cmp(&x),
-            PacketElement::Array(_) => cmp_int_array(a, &b_inner[0])
-        }.unwrap();
-        if ord != Ordering::Equal {
-            return Some(ord);
-        }
-        else {
-            return 1.partial_cmp(&b_inner.len());
+impl From<&Packet> for serde_json::Value {
+    fn from(packet: &Packet) -> Self
+    {
+        match packet {
+            Packet::Int(x) => Self::from(*x),
+            Packet::List(list) => Self::Array(list.iter().map(Self::from).collect()),
         }
     }
 }
 
-impl PartialOrd for PacketElement {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-
-        use std::cmp::Ordering;
+impl From<Packet> for serde_json::Value {
+    fn from(packet: Packet) -> Self
+    {
+        Self::from(&packet)
+    }
+}
 
-        match (self, other) {
-            (Self::Int(a), Self::Int(b)) => a.partial_cmp(b),
-            (Self::Array(a), Self::Array(b)) => {
+/// Only arrays and integers fitting in a `u32` are accepted
+impl TryFrom<&serde_json::Value> for Packet {
+    type Error = Error;
 
-                for i in 0..(a.len().min(b.len())) {
-                    let ord = a[i].partial_cmp(&b[i]).unwrap();
-                    if ord != Ordering::Equal {
-                        return Some(ord);
-                    }
-                }
-                
-                a.len().partial_cmp(&b.len())
-
-            }
-            (a, b) => {
-                if a.is_int() {
-                    cmp_int_array(a, b)
-                }
-                else {
-                    cmp_int_array(b, a).map(Ordering::reverse)
-                }
-            }
+    fn try_from(value: &serde_json::Value) -> Result<Self>
+    {
+        match value {
+            serde_json::Value::Number(number) => number.as_u64()
+                .and_then(|x| u32::try_from(x).ok())
+                .map(Self::Int)
+                .ok_or_else(|| Error::Unsupported(format!("packet integer {}", number))),
+            serde_json::Value::Array(array) => array.iter().map(Self::try_from).collect::<Result<_>>().map(Self::List),
+            _ => Err(Error::Unsupported(format!("packet value {}", value))),
         }
     }
 }
 
-impl Ord for PacketElement {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap()
+impl TryFrom<serde_json::Value> for Packet {
+    type Error = Error;
+
+    fn try_from(value: serde_json::Value) -> Result<Self>
+    {
+        Self::try_from(&value)
     }
 }
 
 mod parse {
     use crate::days::parse::*;
     use crate::days::parse::nom_goes_brrr::*;
+    use nom::combinator::{all_consuming, map};
+    use nom::error::VerboseError;
+    use super::Packet;
 
-    fn parse_packet<'a, E>(input: &'a str) -> IResult<&'a str, super::PacketElement, E>
+    fn parse_packet<'a, E>(i: &'a str) -> IResult<&'a str, Packet, E>
     where E: ParseError<&'a str>
     {
-        let array_start = tag("[");
-        let array_end = tag("]");
-        let parse_packet_el_int = |i| {
-            match parse_int::<_, E>(i) {
-                Ok((i, x)) => Ok((i, super::PacketElement::Int(x))),
-                Err(a) => Err(a),
-            }
-        };
-
-        let (i, _) = array_start(input)?;
-
-        let (i, list) = separated_list0(tag(","), 
-            alt((
-                parse_packet_el_int,
-                parse_packet
-            ))
-        )(i)?;
-
-        let (i, _) = array_end(i)?;
+        alt((
+            map(parse_int::<u32, E>, Packet::Int),
+            map(delimited(tag("["), separated_list0(tag(","), parse_packet), tag("]")), Packet::List),
+        ))(i)
+    }
 
-        Ok((i, super::PacketElement::Array(list)))
+    pub(super) fn parse_whole_packet(input: &str) -> super::Result<Packet>
+    {
+        let (_, packet) = make_verbose_error_message(input, all_consuming(parse_packet::<VerboseError<&str>>)(input))?;
+        Ok(packet)
     }
 
     pub(super) fn parse_and_do_for_packet_pair<F>(input: &str, mut func: F) -> super::Result<()>
-    where F: FnMut(super::PacketElement, super::PacketElement) -> super::Result<()>
+    where F: FnMut(Packet, Packet) -> super::Result<()>
     {
         let mut i = input;
-        while i.starts_with("[") {
+        while i.starts_with('[') {
             let mut parse_packet_line = terminated(parse_packet, newline);
             let packet_a;
             let packet_b;
@@ -188,7 +210,7 @@ mod parse {
         Ok(())
     }
 
-    pub(super) fn parse_and_collect_packets(input: &str) -> super::Result<Vec<super::PacketElement>>
+    pub(super) fn parse_and_collect_packets(input: &str) -> super::Result<Vec<Packet>>
     {
         let (_, packets) = make_verbose_error_message(input,
             many0(
@@ -200,4 +222,61 @@ mod parse {
         )?;
         Ok(packets)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::prelude::*;
+    use rand::rngs::StdRng;
+
+    fn random_packet(rng: &mut StdRng, depth: usize) -> Packet
+    {
+        if depth == 0 || rng.gen_bool(0.4) {
+            // mostly small values, sometimes up to the limit
+            let x = if rng.gen_bool(0.9) { rng.gen_range(0..=10) } else { rng.gen() };
+            return Packet::Int(x);
+        }
+        let len = rng.gen_range(0..=4);
+        Packet::List((0..len).map(|_| random_packet(rng, depth - 1)).collect())
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = StdRng::seed_from_u64(13);
+        for _ in 0..1000 {
+            let packet = random_packet(&mut rng, 5);
+            let text = packet.to_string();
+            assert_eq!(text.parse::<Packet>().unwrap(), packet, "{}", text);
+
+            let json = serde_json::Value::from(&packet);
+            assert_eq!(json.to_string(), text);
+            assert_eq!(Packet::try_from(&json).unwrap(), packet);
+        }
+
+        assert!("[1,[2,3]".parse::<Packet>().is_err());
+        assert!("[1,[2,3]] ".parse::<Packet>().is_err());
+        assert!("[-1]".parse::<Packet>().is_err());
+        assert!(Packet::try_from(serde_json::json!([1, "2"])).is_err());
+        assert!(Packet::try_from(serde_json::json!([1, 2.5])).is_err());
+    }
+
+    #[test]
+    fn test_order() {
+        let parse = |s: &str| s.parse::<Packet>().unwrap();
+        assert_eq!(parse("[1,1,3,1,1]").signal_cmp(&parse("[1,1,5,1,1]")), Ordering::Less);
+        assert_eq!(parse("[[1],[2,3,4]]").signal_cmp(&parse("[[1],4]")), Ordering::Less);
+        assert_eq!(parse("[9]").signal_cmp(&parse("[[8,7,6]]")), Ordering::Greater);
+        assert_eq!(parse("[[[]]]").signal_cmp(&parse("[[]]")), Ordering::Greater);
+        assert_eq!(parse("[[2]]").signal_cmp(&parse("2")), Ordering::Equal);
+        assert_eq!(parse("[[2]]").cmp(&parse("2")), Ordering::Greater);
+
+        let mut rng = StdRng::seed_from_u64(31);
+        let mut packets: Vec<Packet> = (0..300).map(|_| random_packet(&mut rng, 3)).collect();
+        packets.sort();
+        for pair in packets.windows(2) {
+            assert_ne!(pair[0].signal_cmp(&pair[1]), Ordering::Greater);
+            assert_eq!(pair[0].cmp(&pair[1]) == Ordering::Equal, pair[0] == pair[1]);
+        }
+    }
+}
@@ -1,52 +1,135 @@
 use crate::days::internal_common::*;
-use std::fmt;
-use macro_lib::Grid2D;
+use macro_lib::{Grid2D, GridCell};
+use crate::days::directions::Direction4 as Direction;
+use crate::days::image::{Image, RenderTarget};
+use crate::days::simulation::{self, RunOptions, Simulation, StepOutcome};
 
 pub fn day_14_part_1<Input>(input: &mut Input) -> Result<()>
 where Input: Read
+{
+    solve_part_1(input, &RunOptions::default()).map(|_| ())
+}
+
+pub fn day_14_part_1_render<Input>(input: &mut Input, target: &RenderTarget) -> Result<()>
+where Input: Read
+{
+    let grid = solve_part_1(input, &RunOptions::default())?;
+    target.save(&grid.to_image())?;
+    Ok(())
+}
+
+pub fn day_14_part_1_simulate<Input>(input: &mut Input, options: &RunOptions) -> Result<()>
+where Input: Read
+{
+    solve_part_1(input, options).map(|_| ())
+}
+
+fn solve_part_1<Input>(input: &mut Input, options: &RunOptions) -> Result<Grid>
+where Input: Read
 {
     let input = get_whole_input_as_string(input)?;
     let lines = parse::parse_and_collect_lines(&input)?;
     
-    let mut grid = Grid::from_lines(&lines);
+    let mut sim = SandSimulation::new(Grid::from_lines(&lines));
+    simulation::run(&mut sim, options, &mut std::io::stdout().lock())?;
+    print_final_state(&sim.grid);
 
-    let mut num_units_at_rest = 0;
-    while let SandUnitFinalState::Rest(_) = grid.pour_sand() {
-        num_units_at_rest += 1;
-    }
+    println!("Num of units at rest is {}", sim.num_units_at_rest);
+
+    Ok(sim.grid)
+}
+
+pub fn day_14_part_2<Input>(input: &mut Input) -> Result<()>
+where Input: Read
+{
+    let input = get_whole_input_as_string(input)?;
+    let mut grid = Grid::with_floor(parse::parse_and_collect_lines(&input)?);
+
+    let num_units_at_rest = grid.fill_sand_above_floor();
+    print_final_state(&grid);
 
     println!("Num of units at rest is {}", num_units_at_rest);
 
     Ok(())
 }
 
-pub fn day_14_part_2<Input>(input: &mut Input) -> Result<()>
+pub fn day_14_part_2_render<Input>(input: &mut Input, target: &RenderTarget) -> Result<()>
+where Input: Read
+{
+    let grid = solve_part_2(input, &RunOptions::default())?;
+    target.save(&grid.to_image())?;
+    Ok(())
+}
+
+pub fn day_14_part_2_simulate<Input>(input: &mut Input, options: &RunOptions) -> Result<()>
+where Input: Read
+{
+    solve_part_2(input, options).map(|_| ())
+}
+
+fn solve_part_2<Input>(input: &mut Input, options: &RunOptions) -> Result<Grid>
 where Input: Read
 {
     let input = get_whole_input_as_string(input)?;
-    let mut lines = parse::parse_and_collect_lines(&input)?;
-    
-    // add floor and make it large enough
-    let (min_x, max_x, max_y) = get_min_max(&lines);
-    let full_height = max_y + 2;
-    let min_x = min_x.min(500 - full_height);
-    let max_x = max_x.max(500 + full_height);
-    lines.push(vec![Point {x: min_x, y: full_height}, Point {x: max_x, y: full_height}]);
-
-    let mut grid = Grid::from_lines(&lines);
-
-    let source_id = 500 - grid.min_original_x as usize;
-    let mut num_units_at_rest = 0;
-    while let SandUnitFinalState::Rest(node) = grid.pour_sand() {
-        num_units_at_rest += 1;
-        if node.id == source_id {
-            break;
+    let lines = parse::parse_and_collect_lines(&input)?;
+
+    let mut sim = SandSimulation::new(Grid::with_floor(lines));
+    simulation::run(&mut sim, options, &mut std::io::stdout().lock())?;
+    print_final_state(&sim.grid);
+
+    println!("Num of units at rest is {}", sim.num_units_at_rest);
+
+    Ok(sim.grid)
+}
+
+fn print_final_state(grid: &Grid)
+{
+    if is_verbose() {
+        print!("{}", grid);
+    }
+}
+
+// one unit of sand is poured at each step, until one falls into the abyss or the source is covered
+struct SandSimulation {
+    grid: Grid,
+    num_units_at_rest: usize,
+    done: bool,
+}
+
+impl SandSimulation {
+    fn new(grid: Grid) -> Self
+    {
+        Self {
+            grid,
+            num_units_at_rest: 0,
+            done: false,
         }
     }
+}
 
-    println!("Num of units at rest is {}", num_units_at_rest);
+impl Simulation for SandSimulation {
+    fn step(&mut self) -> StepOutcome
+    {
+        let source_id = 500 - self.grid.min_original_x as usize;
+        match self.grid.pour_sand() {
+            SandUnitFinalState::Rest(node) => {
+                self.num_units_at_rest += 1;
+                self.done = node.id == source_id;
+            },
+            SandUnitFinalState::Abyss => self.done = true,
+        }
+        if self.done { StepOutcome::Done } else { StepOutcome::Continue }
+    }
 
-    Ok(())
+    fn render(&self) -> String
+    {
+        self.grid.to_string()
+    }
+
+    fn is_done(&self) -> bool
+    {
+        self.done
+    }
 }
 
 fn get_min_max(lines: &Vec<Line>) -> (u32, u32, u32) // min_x, max_x, max_y
@@ -74,60 +157,27 @@ type Line = Vec<Point>;
 
 type Point = crate::days::points::Point2<u32>;
 
-enum Direction {
-    Up,
-    Down,
-    Right,
-    Left
-}
-
 impl Point {
     fn get_direction_to(&self, other: &Point) -> Direction
     {
-        let d_x = other.x as i32 - self.x as i32;
-        let d_y = other.y as i32 - self.y as i32;
-        if d_x > 0 {
-            return Direction::Right;
-        }
-        else if d_x < 0 {
-            return Direction::Left;
-        }
-        if d_y > 0 {
-            return Direction::Down;
-        }
-        else if d_y < 0 {
-            return Direction::Up;
-        }
-        else {
-            panic!("No direction");
-        }
+        let delta = crate::days::points::Point2::from_xy(
+            other.x as i32 - self.x as i32,
+            other.y as i32 - self.y as i32
+        );
+        Direction::from_point(&delta.signum()).expect("No direction")
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug, GridCell)]
 enum PointContent {
+    #[cell('.')]
     Air,
+    #[cell('o')]
     Sand,
+    #[cell('#')]
     Rock
 }
 
-impl PointContent {
-    fn to_char(&self) -> char
-    {
-        match self {
-            Self::Air => '.',
-            Self::Sand => 'o',
-            Self::Rock => '#',
-        }
-    }
-}
-
-impl fmt::Debug for PointContent {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.to_char())
-    }
-}
-
 #[derive(Debug, Grid2D)]
 struct Grid {
     data: Vec<PointContent>,
@@ -136,6 +186,18 @@ struct Grid {
     min_original_x: u32
 }
 
+impl std::fmt::Display for Grid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in self.data.chunks(self.width) {
+            for content in row {
+                write!(f, "{}", content)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone)]
 struct Node {
     id: usize,
@@ -169,19 +231,54 @@ impl Grid {
 
         // translate points to local coordinates within grid
         let lines: Vec<Line> = lines.iter().map(
-            |line| line.iter().map(|point| Point {x: point.x - min_x, y: point.y}).collect()
+            |line| line.iter().map(|point| Point::from_xy(point.x - min_x, point.y)).collect()
         ).collect();
         grid.put_rock_from_lines(&lines);
 
         grid
     }
 
+    /// With a floor two units below the lowest rock, wide enough for the sand to never fall off
+    fn with_floor(mut lines: Vec<Line>) -> Self
+    {
+        let (min_x, max_x, max_y) = get_min_max(&lines);
+        let full_height = max_y + 2;
+        let min_x = min_x.min(500 - full_height);
+        let max_x = max_x.max(500 + full_height);
+        lines.push(vec![Point::from_xy(min_x, full_height), Point::from_xy(max_x, full_height)]);
+        Self::from_lines(&lines)
+    }
+
+    /// Same final state as pouring sand until the source is covered, for a grid made by `with_floor`.
+    /// Sand ends up in every cell of the triangle below the source which is not rock and has sand
+    /// in one of the three cells above it, so the triangle is filled row by row.
+    /// Returns the number of units at rest.
+    fn fill_sand_above_floor(&mut self) -> usize
+    {
+        let source_x = 500 - self.min_original_x as usize;
+        self.data[source_x] = PointContent::Sand;
+        let mut num_units_at_rest = 1;
+        // the last row is the floor
+        for y in 1..(self.height - 1) {
+            for x in (source_x - y)..=(source_x + y) {
+                let id = y * self.width + x;
+                let above = (y - 1) * self.width;
+                let is_fed = (x - 1..=x + 1).any(|above_x| self.data[above + above_x] == PointContent::Sand);
+                if is_fed && self.data[id] == PointContent::Air {
+                    self.data[id] = PointContent::Sand;
+                    num_units_at_rest += 1;
+                }
+            }
+        }
+        num_units_at_rest
+    }
+
     fn put_rock_from_lines(&mut self, lines: &Vec<Line>)
     {
         for line in lines {
             for i in 0..(line.len() - 1) {
                 let dir = line[i].get_direction_to(&line[i + 1]);
-                let mut current_point = line[i].clone();
+                let mut current_point = line[i];
                 let mut current_node = self.get_node_from_id(current_point.y as usize * self.width + current_point.x as usize);
                 self.data[current_node.id] = PointContent::Rock;
                 while current_point != line[i + 1] {
@@ -246,16 +343,14 @@ impl Grid {
         SandUnitFinalState::Abyss
     }
 
-    // fn print(&self)
-    // {
-    //     for i_y in 0..self.height {
-    //         let mut line_buffer = String::with_capacity(self.width);
-    //         for i_x in 0..self.width {
-    //             line_buffer.push(self.data[i_y * self.width + i_x].to_char());
-    //         }
-    //         println!("{}", line_buffer);
-    //     }
-    // }
+    fn to_image(&self) -> Image
+    {
+        Image::from_grid(&self.data, self.width, |content| match content {
+            PointContent::Air => [16, 16, 32],
+            PointContent::Sand => [230, 190, 100],
+            PointContent::Rock => [110, 110, 110],
+        })
+    }
 }
 
 mod parse {
@@ -266,7 +361,7 @@ mod parse {
     {
         let parse_point = map_res(
             separated_pair(parse_int, tag(","), parse_int),
-            |(x, y)| Ok::<_, ()>(super::Point { x, y }));
+            |(x, y)| Ok::<_, ()>(super::Point::from_xy(x, y)));
         let parse_line = separated_list0(tag(" -> "), parse_point);
 
         let (_, lines) = make_verbose_error_message(input,
@@ -276,4 +371,24 @@ mod parse {
         )?;
         Ok(lines)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXAMPLE: &str = "498,4 -> 498,6 -> 496,6\n503,4 -> 502,4 -> 502,9 -> 494,9\n";
+
+    #[test]
+    fn test_fill_matches_simulation() {
+        let lines = parse::parse_and_collect_lines(EXAMPLE).unwrap();
+        let mut filled = Grid::with_floor(lines.clone());
+        assert_eq!(filled.fill_sand_above_floor(), 93);
+
+        let mut sim = SandSimulation::new(Grid::with_floor(lines));
+        simulation::run_to_end(&mut sim);
+        assert_eq!(sim.num_units_at_rest, 93);
+        assert_eq!(filled.data, sim.grid.data);
+        assert_eq!(filled.to_string().lines().nth(7), Some("....oooo.oooo#ooooo...."));
+    }
+}
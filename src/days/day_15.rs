@@ -1,5 +1,6 @@
 use crate::days::internal_common::*;
 use std::ops::Range;
+use crate::days::ranges::RangeSet;
 use std::collections::BTreeSet;
 
 pub fn day_15_part_1<Input>(input: &mut Input) -> Result<()>
@@ -9,15 +10,11 @@ where Input: Read
     let points = parse::parse_and_collect(&input)?;
     let y_scan = 2_000_000;
     
-    let mut ranges: Vec<Range<i32>> = Vec::new();
-    for sensor_beacon in &points {
-        if let Some(intersec) = get_range_intersect(y_scan, sensor_beacon) {
-            ranges.push(intersec);
-        }
-    }
-    combine_overlapping_ranges(&mut ranges);
-    
-    let sum: usize = ranges.iter().map(|range| range.len()).sum();
+    let ranges: RangeSet<i32> = points.iter()
+        .filter_map(|sensor_beacon| get_range_intersect(y_scan, sensor_beacon))
+        .collect();
+
+    let sum = ranges.total_len() as usize;
     let unique_beacons = get_unique_beacons(&points);
     let sum: usize = sum - unique_beacons.iter().filter(|beacon| beacon.y == y_scan).count();
     println!("Answer is {}", sum);
@@ -31,32 +28,117 @@ where Input: Read
     let input = get_whole_input_as_string(input)?;
     let points = parse::parse_and_collect(&input)?;
 
-    for y_scan in 0..=4_000_000 {
-    
-        let mut ranges: Vec<Range<i32>> = Vec::new();
-        for sensor_beacon in &points {
-            if let Some(intersec) = get_range_intersect(y_scan, sensor_beacon) {
-                ranges.push(intersec);
+    let search_box = SearchBox { min: Point::from_xy(0, 0), max: Point::from_xy(4_000_000, 4_000_000) };
+    let uncovered = find_uncovered_points(&points, &search_box, MAX_LISTED_POINTS);
+    match (uncovered.points.as_slice(), uncovered.complete) {
+        ([], _) => return Err(Error::NoSolution),
+        ([p], true) => println!("Signal is {}", tuning_frequency(p)),
+        (points, complete) => {
+            if complete {
+                println!("{} possible positions:", points.len());
+            }
+            else {
+                println!("More than {} possible positions, the first ones being:", points.len());
+            }
+            for p in points {
+                println!("{},{} with signal {}", p.x, p.y, tuning_frequency(p));
             }
+        },
+    }
+
+    Ok(())
+}
+
+const MAX_LISTED_POINTS: usize = 1000;
+
+fn tuning_frequency(p: &Point) -> i64
+{
+    p.x as i64 * 4_000_000 + p.y as i64
+}
+
+/// Area searched for the distress beacon, bounds included
+#[derive(Debug, Clone, Copy)]
+pub struct SearchBox {
+    pub min: Point,
+    pub max: Point,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UncoveredPoints {
+    /// Sorted by y, then x
+    pub points: Vec<Point>,
+    /// False if there are more points than the limit
+    pub complete: bool,
+}
+
+/// Points of `search_box` out of reach of every sensor, at most `limit` of them.
+/// The uncovered parts of a row only appear or disappear where the lines running along the sensor ranges
+/// just out of reach cross each other or the edges of the box, so only the rows around these crossings,
+/// and the rows below them while they have uncovered points, are scanned.
+pub fn find_uncovered_points(sensors: &[(Point, Point)], search_box: &SearchBox, limit: usize) -> UncoveredPoints
+{
+    let (min, max) = (search_box.min, search_box.max);
+
+    // boundaries at distance r + 1, as lines x + y = sum and x - y = diff
+    let mut sums = BTreeSet::new();
+    let mut diffs = BTreeSet::new();
+    for (sensor, beacon) in sensors {
+        let r = sensor.manhattan_distance(beacon);
+        for offset in [-(r + 1), r + 1] {
+            sums.insert(sensor.x + sensor.y + offset);
+            diffs.insert(sensor.x - sensor.y + offset);
         }
-        combine_overlapping_ranges(&mut ranges);
-        
-        if ranges.len() > 1 {
-            let x = ranges[0].end;
-            let signal = x as usize * 4_000_000 + y_scan as usize;
-            println!("Signal is {}", signal);
-            return Ok(());
+    }
+
+    let mut event_rows = BTreeSet::from([min.y, max.y]);
+    let mut add_around = |y: i32| {
+        event_rows.extend((y - 1..=y + 1).filter(|y| (min.y..=max.y).contains(y)));
+    };
+    for &sum in &sums {
+        for &diff in &diffs {
+            // the lines may cross between two rows
+            add_around((sum - diff).div_euclid(2));
+            add_around((sum - diff + 1).div_euclid(2));
         }
+        add_around(sum - min.x);
+        add_around(sum - max.x);
+    }
+    for &diff in &diffs {
+        add_around(min.x - diff);
+        add_around(max.x - diff);
     }
 
-    Err(Error::NoSolution)
+    let mut uncovered = UncoveredPoints { points: Vec::new(), complete: true };
+    let event_rows: Vec<i32> = event_rows.into_iter().collect();
+    for (i, &event_row) in event_rows.iter().enumerate() {
+        let next_event_row = event_rows.get(i + 1).copied().unwrap_or(max.y + 1);
+        for y_scan in event_row..next_event_row {
+            let ranges: RangeSet<i32> = sensors.iter()
+                .filter_map(|sensor_beacon| get_range_intersect(y_scan, sensor_beacon))
+                .collect();
+            let gaps = ranges.complement(min.x..(max.x + 1));
+            if gaps.is_empty() {
+                break;
+            }
+            for gap in gaps.ranges() {
+                for x in gap.clone() {
+                    if uncovered.points.len() == limit {
+                        uncovered.complete = false;
+                        return uncovered;
+                    }
+                    uncovered.points.push(Point::from_xy(x, y_scan));
+                }
+            }
+        }
+    }
+    uncovered
 }
 
 fn get_unique_beacons(points: &Vec<(Point, Point)>) -> BTreeSet<Point>
 {
     let mut unique_beacons = BTreeSet::new();
     for (_, beacon) in points {
-        unique_beacons.insert(beacon.clone());
+        unique_beacons.insert(*beacon);
     }
     unique_beacons
 }
@@ -64,7 +146,7 @@ fn get_unique_beacons(points: &Vec<(Point, Point)>) -> BTreeSet<Point>
 fn get_range_intersect(y: i32, sensor_beacon: &(Point, Point)) -> Option<Range<i32>>
 {
     let (sensor, beacon) = sensor_beacon;
-    let d = sensor.distance(&beacon);
+    let d = sensor.manhattan_distance(beacon);
     if (y - sensor.y).abs() > d {
         return None;
     }
@@ -72,52 +154,7 @@ fn get_range_intersect(y: i32, sensor_beacon: &(Point, Point)) -> Option<Range<i
     Some((sensor.x - half_range_len)..(sensor.x + 1 + half_range_len))
 }
 
-fn combine_overlapping_ranges(combined_ranges: &mut Vec<Range<i32>>)
-{
-    combined_ranges.sort_by(|a, b| a.start.cmp(&b.start));
-
-    let mut i = 0;
-
-    while i + 1 < combined_ranges.len() {
-        let mut j = i;
-        while j + 1 < combined_ranges.len() && combined_ranges[j + 1].start <= combined_ranges[i].end {
-            j += 1;
-        }
-        let max_end = combined_ranges[i..=j].iter().max_by(|a, b| a.end.cmp(&b.end)).unwrap().end;
-        combined_ranges[i] = combined_ranges[i].start..max_end;
-        combined_ranges.drain((i + 1)..=j);
-
-        if j - i == 0 {
-            i += 1;
-        }
-    }
-}
-
-// fn range_intersection(a: &Range<i32>, b: &Range<i32>) -> Option<Range<i32>>
-// {
-//     if a.start > b.start {
-//         return range_intersection(b, a);
-//     }
-
-//     // we have a.start < b.start
-
-//     if a.end <= b.start {
-//         return None;
-//     }
-
-//     // we have a.end > b.start
-
-//     Some(b.start..b.end.min(a.end))
-// }
-
-type Point = crate::days::points::Point2<i32>;
-
-impl Point {
-    fn distance(&self, other: &Self) -> i32
-    {
-        (self.x - other.x).abs() + (self.y - other.y).abs()
-    }
-}
+pub type Point = crate::days::points::Point2<i32>;
 
 mod parse {
     use crate::days::parse::*;
@@ -129,7 +166,7 @@ mod parse {
     {
         map_res(
             separated_pair(preceded(tag("x="), parse_int), tag(", y="), parse_int),
-            |(x, y)| Ok::<_, ()>(Point { x, y })
+            |(x, y)| Ok::<_, ()>(Point::from_xy(x, y))
         )(input)
     }
 
@@ -147,4 +184,55 @@ mod parse {
         )?;
         Ok(sensor_beacon_pairs)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+Sensor at x=2, y=18: closest beacon is at x=-2, y=15
+Sensor at x=9, y=16: closest beacon is at x=10, y=16
+Sensor at x=13, y=2: closest beacon is at x=15, y=3
+Sensor at x=12, y=14: closest beacon is at x=10, y=16
+Sensor at x=10, y=20: closest beacon is at x=10, y=16
+Sensor at x=14, y=17: closest beacon is at x=10, y=16
+Sensor at x=8, y=7: closest beacon is at x=2, y=10
+Sensor at x=2, y=0: closest beacon is at x=2, y=10
+Sensor at x=0, y=11: closest beacon is at x=2, y=10
+Sensor at x=20, y=14: closest beacon is at x=25, y=17
+Sensor at x=17, y=20: closest beacon is at x=21, y=22
+Sensor at x=16, y=7: closest beacon is at x=15, y=3
+Sensor at x=14, y=3: closest beacon is at x=15, y=3
+Sensor at x=20, y=1: closest beacon is at x=15, y=3
+";
+
+    #[test]
+    fn test_uncovered_points() {
+        let points = parse::parse_and_collect(EXAMPLE).unwrap();
+        let search_box = SearchBox { min: Point::from_xy(0, 0), max: Point::from_xy(20, 20) };
+        let uncovered = find_uncovered_points(&points, &search_box, 10);
+        assert_eq!(uncovered, UncoveredPoints { points: vec![Point::from_xy(14, 11)], complete: true });
+        assert_eq!(tuning_frequency(&Point::from_xy(14, 11)), 56000011);
+
+        let search_box = SearchBox { min: Point::from_xy(0, 0), max: Point::from_xy(20, 10) };
+        assert!(find_uncovered_points(&points, &search_box, 10).points.is_empty());
+
+        // compared to every point of boxes with several uncovered points
+        for (min, max) in [((-10, -10), (30, 30)), ((10, 8), (26, 13)), ((-4, 14), (3, 24)), ((14, 11), (14, 11))] {
+            let search_box = SearchBox { min: Point::from_xy(min.0, min.1), max: Point::from_xy(max.0, max.1) };
+            let uncovered = find_uncovered_points(&points, &search_box, usize::MAX);
+            let all: Vec<Point> = (min.1..=max.1)
+                .flat_map(|y| (min.0..=max.0).map(move |x| Point::from_xy(x, y)))
+                .filter(|p| points.iter().all(|(sensor, beacon)| sensor.manhattan_distance(p) > sensor.manhattan_distance(beacon)))
+                .collect();
+            assert!(uncovered.complete);
+            assert_eq!(uncovered.points, all);
+        }
+
+        let search_box = SearchBox { min: Point::from_xy(-10, -10), max: Point::from_xy(30, 30) };
+        let uncovered = find_uncovered_points(&points, &search_box, 5);
+        assert!(!uncovered.complete);
+        assert_eq!(uncovered.points.len(), 5);
+    }
+}
@@ -1,6 +1,10 @@
 use crate::days::internal_common::*;
 use std::collections::HashMap;
 use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::fmt::Write;
+use std::path::Path;
+use crate::days::annealing::{self, AnnealingParams, AnnealingState};
 
 fn get_structs_for_solv<Input>(input: &mut Input) -> Result<(Vec<Valve>, Vec<ValveForSolv>, ValveId)>
 where Input: Read
@@ -9,14 +13,15 @@ where Input: Read
     let mut valves = parse::parse_and_collect(&input)?;
     remap_valves_ids(&mut valves);
 
+    // the solvers only go to the valves with a flow, from AA
+    let mut all_distances = compute_all_distances(&valves);
     let mut valves_for_solv: Vec<ValveForSolv> = Vec::new();
-    for v in &valves {
-        let distances;
-        if v.flow_rate > 0 || v.name == "AA" {
-            distances = compute_distances(v.id, &valves);
+    for (v, distances) in valves.iter().zip(&mut all_distances) {
+        let distances = if v.flow_rate > 0 || v.name == "AA" {
+            std::mem::take(distances)
         } else {
-            distances = Vec::new();
-        }
+            Vec::new()
+        };
         valves_for_solv.push(ValveForSolv {def: v.clone(), distances});
     }
     let initial_id = valves.iter().find(|v| v.name == "AA").unwrap().id;
@@ -29,43 +34,21 @@ where Input: Read
 {
     let (valves, valves_for_solv, initial_id) = get_structs_for_solv(input)?;
 
-    let non_null_flow_valves: Vec<ValveId> = valves
-        .iter()
-        .filter(|v| v.flow_rate > 0)
-        .map(|v| v.id)
-        .collect();
-
-    let mut visit_order_generator = VisitOrderGenerator::new(non_null_flow_valves);
-
-    let annealing_temp = 2000.0;
-    let annealing_temp_decrease_factor = 0.995;
-    let annealing_final_temp = 2.0;
-    let annealing_it_length = 100;
-    let print_period = 100;
-    let mut num_it = 0;
-    let mut score = 0;
-    let mut annealing = Annealing::new(annealing_temp, annealing_temp_decrease_factor, annealing_final_temp);
-
-    while annealing.above_final_temp() {
-
-        for _i in 0..annealing_it_length {
-            visit_order_generator.swap();
-            let next_score = get_score(30, initial_id, &visit_order_generator.current_visit_order, &valves_for_solv);
-            if annealing.transition(score, next_score) {
-                score = next_score;
-            } else {
-                visit_order_generator.cancel_swap();
-            }
-        }
-        annealing.decrease_temp();
-        num_it += 1;
-        if num_it % print_period == 0 {
-            println!("score: {}", score);
-        }
+    let best = best_score_alone(30, initial_id, &valves_for_solv)?;
+    println!("Best score {}", best);
+
+    if is_verbose() {
+        let non_null_flow_valves = get_non_null_flow_valves(&valves);
+        let plan = VisitPlan {
+            visit_orders: vec![non_null_flow_valves],
+            previous_visit_orders: Vec::new(),
+            time: 30,
+            start_id: initial_id,
+            valves: &valves_for_solv,
+        };
+        check_annealing(plan, best)?;
     }
 
-    dbg!(&visit_order_generator.current_visit_order, score, num_it);
-    
     Ok(())
 }
 
@@ -74,183 +57,143 @@ where Input: Read
 {
     let (valves, valves_for_solv, initial_id) = get_structs_for_solv(input)?;
 
-    let non_null_flow_valves: Vec<ValveId> = valves
-        .iter()
-        .filter(|v| v.flow_rate > 0)
-        .map(|v| v.id)
-        .collect();
-
-    let mut my_visit_order: Vec<ValveId> = non_null_flow_valves.clone();
-    let mut el_visit_order: Vec<ValveId> = Vec::with_capacity(non_null_flow_valves.len());
-    let mut my_visit_order_tmp: Vec<ValveId> = Vec::with_capacity(non_null_flow_valves.len());
-    let mut el_visit_order_tmp: Vec<ValveId> = Vec::with_capacity(non_null_flow_valves.len());
-
-    let annealing_temp = 2000.0;
-    let annealing_temp_decrease_factor = 0.995;
-    let annealing_final_temp = 2.0;
-    let annealing_it_length = 100;
-    let print_period = 100;
-    let mut num_it = 0;
-    let mut score = 0;
-    let mut annealing = Annealing::new(annealing_temp, annealing_temp_decrease_factor, annealing_final_temp);
-
-    let mut permut_rng = rand::rngs::StdRng::seed_from_u64(23456);
-
-    while annealing.above_final_temp() {
-
-        for _i in 0..annealing_it_length {
-
-            my_visit_order_tmp.clone_from(&my_visit_order);
-            el_visit_order_tmp.clone_from(&el_visit_order);
-            if permut_rng.gen_bool(0.5) {
-                let swap_in_my;
-                if my_visit_order_tmp.len() < 2 {
-                    swap_in_my = false;
-                }
-                else if el_visit_order_tmp.len() < 2 {
-                    swap_in_my = true;
-                }
-                else {
-                    swap_in_my = permut_rng.gen_bool(0.5);
-                }
-                if swap_in_my {
-                    VisitOrderGenerator::swap_in_place(&mut permut_rng, &mut my_visit_order_tmp);
-                }
-                else {
-                    VisitOrderGenerator::swap_in_place(&mut permut_rng, &mut el_visit_order_tmp);
-                }
-            }
-            else {
-                VisitOrderGenerator::move_between_two(&mut permut_rng, &mut my_visit_order_tmp, &mut el_visit_order_tmp);
-            }
-
-            let next_score = get_score_with_elephant(initial_id, &my_visit_order_tmp, &el_visit_order_tmp, &valves_for_solv);
-            if annealing.transition(score, next_score) {
-                score = next_score;
-                my_visit_order.clone_from(&my_visit_order_tmp);
-                el_visit_order.clone_from(&el_visit_order_tmp);
-            }
-        }
-
-        annealing.decrease_temp();
-        num_it += 1;
-        if num_it % print_period == 0 {
-            println!("score: {}", score);
-        }
+    let best = best_score_with_elephant(26, initial_id, &valves_for_solv)?;
+    println!("Best score {}", best);
+
+    if is_verbose() {
+        // I start with all the valves, the elephant with none
+        let non_null_flow_valves = get_non_null_flow_valves(&valves);
+        let plan = VisitPlan {
+            visit_orders: vec![non_null_flow_valves, Vec::new()],
+            previous_visit_orders: Vec::new(),
+            time: 26,
+            start_id: initial_id,
+            valves: &valves_for_solv,
+        };
+        check_annealing(plan, best)?;
     }
 
-    println!("{:?} {:?}", &my_visit_order, &el_visit_order);
-    dbg!(score, num_it);
-    
     Ok(())
 }
 
-struct Annealing {
-    temp: f32,
-    temp_decrease_factor: f32,
-    final_temp: f32,
-    transition_rng: rand::rngs::StdRng
+/// Writes the tunnels, then the distances between the valves the solvers go to, as two Graphviz graphs
+pub fn day_16_part_1_dot<Input>(input: &mut Input, path: &Path) -> Result<()>
+where Input: Read
+{
+    let (_, valves_for_solv, _) = get_structs_for_solv(input)?;
+
+    let dot = tunnels_to_dot(&valves_for_solv) + &distances_to_dot(&valves_for_solv);
+    std::fs::write(path, dot)?;
+    Ok(())
 }
 
-impl Annealing {
-    fn new(temp: f32, temp_decrease_factor: f32, final_temp: f32) -> Self {
-        Self {
-            temp,
-            temp_decrease_factor,
-            final_temp,
-            transition_rng: rand::rngs::StdRng::seed_from_u64(54321)
-        }
-    }
+pub fn day_16_part_2_dot<Input>(input: &mut Input, path: &Path) -> Result<()>
+where Input: Read
+{
+    day_16_part_1_dot(input, path)
+}
 
-    fn decrease_temp(&mut self) {
-        self.temp *= self.temp_decrease_factor;
-    }
+// the annealing gives no guarantee, compare it to the optimum
+fn check_annealing(plan: VisitPlan, optimum: Score) -> Result<()>
+{
+    let res = annealing::anneal(plan, &AnnealingParams::default(), print_progress)?;
 
-    fn transition(&mut self, score: Score, next_score: Score) -> bool {
-        if next_score > score {
-            return true;
-        }
-        let p = ((next_score as f32 - score as f32) / self.temp).exp();
-        let r = self.transition_rng.gen_range(0.0..1.0);
-        r < p
+    println!("{:?}", &res.best.visit_orders);
+    println!("Annealing score {} after {} iterations", res.best_score as Score, res.num_epochs);
+    if res.best_score as Score == optimum {
+        println!("Annealing found the optimum");
     }
-
-    fn above_final_temp(&self) -> bool {
-        self.temp > self.final_temp
+    else {
+        println!("Annealing missed the optimum by {}", optimum - res.best_score as Score);
     }
+    Ok(())
 }
 
-struct VisitOrderGenerator {
-    current_visit_order: Vec<ValveId>,
-    buffer: Vec<ValveId>,
-    rng: rand::rngs::StdRng
+fn get_non_null_flow_valves(valves: &[Valve]) -> Vec<ValveId>
+{
+    valves
+        .iter()
+        .filter(|v| v.flow_rate > 0)
+        .map(|v| v.id)
+        .collect()
 }
 
-impl VisitOrderGenerator {
-    fn new(current_visit_order: Vec<ValveId>) -> Self {
-        let len = current_visit_order.len();
-        Self {
-            current_visit_order,
-            buffer: vec![0; len],
-            rng: rand::rngs::StdRng::seed_from_u64(23456)
-        }
+fn print_progress(progress: &annealing::Progress)
+{
+    if progress.epoch.is_multiple_of(100) {
+        println!("score: {}", progress.score as Score);
     }
+}
 
-    fn swap(&mut self) {
-        let len = self.current_visit_order.len();
-
-        self.buffer.copy_from_slice(&self.current_visit_order[0..len]);
+// Order in which each explorer opens the valves, explorers move simultaneously
+#[derive(Clone)]
+struct VisitPlan<'a> {
+    visit_orders: Vec<Vec<ValveId>>,
+    previous_visit_orders: Vec<Vec<ValveId>>,
+    time: Score,
+    start_id: ValveId,
+    valves: &'a [ValveForSolv],
+}
 
-        let mut a = self.rng.gen_range(0..len);
-        let mut b = self.rng.gen_range(0..len);
-        if a > b {
-            (a, b) = (b, a);
+impl AnnealingState for VisitPlan<'_> {
+    fn neighbor(&mut self, rng: &mut StdRng) {
+        self.previous_visit_orders.clone_from(&self.visit_orders);
+        let num_explorers = self.visit_orders.len();
+        if num_explorers < 2 || rng.gen_bool(0.5) {
+            let reversible: Vec<usize> = (0..num_explorers).filter(|&i| self.visit_orders[i].len() >= 2).collect();
+            if let Some(&i) = reversible.choose(rng) {
+                reverse_random_slice(rng, &mut self.visit_orders[i]);
+            }
         }
-        for i in 0..(b - a + 1) {
-            self.current_visit_order[a + i] = self.buffer[b - i];
+        else {
+            let a = rng.gen_range(0..num_explorers);
+            let b = (a + rng.gen_range(1..num_explorers)) % num_explorers;
+            let (a, b) = (a.min(b), a.max(b));
+            let (left, right) = self.visit_orders.split_at_mut(b);
+            move_between_two(rng, &mut left[a], &mut right[0]);
         }
     }
 
-    fn cancel_swap(&mut self) {
-        let len = self.current_visit_order.len();
-        self.current_visit_order.copy_from_slice(&self.buffer[0..len]);
+    fn undo(&mut self) {
+        std::mem::swap(&mut self.visit_orders, &mut self.previous_visit_orders);
     }
 
-    fn swap_in_place(rng: &mut rand::rngs::StdRng, visit_order: &mut Vec<ValveId>) {
-        let len = visit_order.len();
-        let mut a = rng.gen_range(0..len);
-        let mut b = rng.gen_range(0..len);
-        if a > b {
-            (a, b) = (b, a);
-        }
-        for i in 0..(b - a + 1) / 2 {
-            visit_order.swap(a + i, b - i);
-        }
+    fn score(&self) -> f64 {
+        self.visit_orders.iter()
+            .map(|visit_order| get_score(self.time, self.start_id, visit_order, self.valves))
+            .sum::<Score>() as f64
     }
+}
 
-    fn move_between_two(rng: &mut rand::rngs::StdRng, visit_order_a: &mut Vec<ValveId>, visit_order_b: &mut Vec<ValveId>) {
-        if visit_order_a.len() == 0 || (visit_order_b.len() != 0 && rng.gen_bool(0.5)) {
-            assert_ne!(visit_order_b.len(), 0);
-            Self::move_between_two(rng, visit_order_b, visit_order_a);
-            return;
-        }
-        assert_ne!(visit_order_a.len(), 0);
+fn reverse_random_slice(rng: &mut StdRng, visit_order: &mut [ValveId]) {
+    let len = visit_order.len();
+    let mut a = rng.gen_range(0..len);
+    let mut b = rng.gen_range(0..len);
+    if a > b {
+        (a, b) = (b, a);
+    }
+    visit_order[a..=b].reverse();
+}
 
-        let t;
-        if rng.gen_bool(0.5) {
-            t = visit_order_a.pop().unwrap();
-        }
-        else {
-            t = visit_order_a.remove(0);
-        }
-        if rng.gen_bool(0.5) {
-            visit_order_b.push(t);
-        }
-        else {
-            visit_order_b.insert(0, t);
-        }
+fn move_between_two(rng: &mut StdRng, visit_order_a: &mut Vec<ValveId>, visit_order_b: &mut Vec<ValveId>) {
+    if visit_order_a.is_empty() || (!visit_order_b.is_empty() && rng.gen_bool(0.5)) {
+        assert!(!visit_order_b.is_empty());
+        move_between_two(rng, visit_order_b, visit_order_a);
+        return;
     }
 
+    let t = if rng.gen_bool(0.5) {
+        visit_order_a.pop().unwrap()
+    }
+    else {
+        visit_order_a.remove(0)
+    };
+    if rng.gen_bool(0.5) {
+        visit_order_b.push(t);
+    }
+    else {
+        visit_order_b.insert(0, t);
+    }
 }
 
 type ValveId = u16;
@@ -269,26 +212,79 @@ struct ValveForSolv {
     distances: Vec<u32>
 }
 
-fn compute_distances(origin: ValveId, valves: &Vec<Valve>) -> Vec<u32>
+// Floyd-Warshall, u32::MAX between valves not connected
+fn compute_all_distances(valves: &[Valve]) -> Vec<Vec<u32>>
 {
-    let mut distances: Vec<u32> = vec![u32::MAX; valves.len()];
-    distances[origin as usize] = 0;
-    compute_distances_recurs(origin, valves, &mut distances);
+    let mut distances = vec![vec![u32::MAX; valves.len()]; valves.len()];
+    for v in valves {
+        distances[v.id as usize][v.id as usize] = 0;
+        for &next in &v.leads_to {
+            distances[v.id as usize][next as usize] = 1;
+        }
+    }
+    for k in 0..valves.len() {
+        for i in 0..valves.len() {
+            if distances[i][k] == u32::MAX {
+                continue;
+            }
+            for j in 0..valves.len() {
+                let through_k = distances[i][k].saturating_add(distances[k][j]);
+                if through_k < distances[i][j] {
+                    distances[i][j] = through_k;
+                }
+            }
+        }
+    }
     distances
 }
 
-fn compute_distances_recurs(current: ValveId, valves: &Vec<Valve>, costs: &mut Vec<u32>)
+fn dot_node(out: &mut String, valve: &Valve)
+{
+    let shape = if valve.name == "AA" { "doublecircle" } else { "circle" };
+    writeln!(out, "    {} [label=\"{}\\n{}\", shape={}];", valve.name, valve.name, valve.flow_rate, shape).unwrap();
+}
+
+// every valve, one edge per tunnel
+fn tunnels_to_dot(valves: &[ValveForSolv]) -> String
 {
-    for &candidate in &valves[current as usize].leads_to {
-        let new_cost = costs[current as usize] + 1;
-        if new_cost < costs[candidate as usize] {
-            costs[candidate as usize] = new_cost;
-            compute_distances_recurs(candidate, valves, costs);
+    let mut ret = String::from("graph tunnels {\n");
+    for v in valves {
+        dot_node(&mut ret, &v.def);
+    }
+    for v in valves {
+        for &next in &v.def.leads_to {
+            // tunnels go both ways, except in broken inputs
+            let is_two_way = valves[next as usize].def.leads_to.contains(&v.def.id);
+            if v.def.id < next || !is_two_way {
+                writeln!(ret, "    {} -- {};", v.def.name, valves[next as usize].def.name).unwrap();
+            }
+        }
+    }
+    ret += "}\n";
+    ret
+}
+
+// the valves kept for the solvers, with their distances
+fn distances_to_dot(valves: &[ValveForSolv]) -> String
+{
+    let kept: Vec<&ValveForSolv> = valves.iter().filter(|v| !v.distances.is_empty()).collect();
+    let mut ret = String::from("graph distances {\n");
+    for v in &kept {
+        dot_node(&mut ret, &v.def);
+    }
+    for (i, a) in kept.iter().enumerate() {
+        for b in &kept[i + 1..] {
+            let distance = a.distances[b.def.id as usize];
+            if distance != u32::MAX {
+                writeln!(ret, "    {} -- {} [label=\"{}\"];", a.def.name, b.def.name, distance).unwrap();
+            }
         }
     }
+    ret += "}\n";
+    ret
 }
 
-fn get_score(time: Score, start_id: ValveId, visit_order: &Vec<ValveId>, valves: &Vec<ValveForSolv>) -> Score
+fn get_score(time: Score, start_id: ValveId, visit_order: &[ValveId], valves: &[ValveForSolv]) -> Score
 {
     let mut time_left = time;
     let mut score: Score = 0;
@@ -305,14 +301,72 @@ fn get_score(time: Score, start_id: ValveId, visit_order: &Vec<ValveId>, valves:
     score
 }
 
-fn get_score_with_elephant(start_id: ValveId,
-    visit_order: &Vec<ValveId>, elephant_visit_order: &Vec<ValveId>,
-    valves: &Vec<ValveForSolv>) -> Score
+type Score = u64;
+
+// useful valves are numbered by their bit in the masks of opened valves
+const MAX_USEFUL_VALVES: usize = 20;
+
+/// Best score of a single explorer
+fn best_score_alone(time: Score, start_id: ValveId, valves: &[ValveForSolv]) -> Result<Score>
 {
-    get_score(26, start_id, visit_order, valves) + get_score(26, start_id, elephant_visit_order, valves)
+    let best_per_mask = get_best_score_per_mask(time, start_id, valves)?;
+    Ok(best_per_mask.into_iter().max().unwrap_or(0))
 }
 
-type Score = u64;
+/// Best score of two explorers moving simultaneously, which open disjoint sets of valves
+fn best_score_with_elephant(time: Score, start_id: ValveId, valves: &[ValveForSolv]) -> Result<Score>
+{
+    let best_per_mask = get_best_score_per_mask(time, start_id, valves)?;
+    let full_mask = best_per_mask.len() - 1;
+
+    // best score opening any subset of the mask
+    let mut best_within_mask = best_per_mask.clone();
+    for bit in 0..full_mask.count_ones() {
+        for mask in 0..best_within_mask.len() {
+            if mask & (1 << bit) != 0 {
+                best_within_mask[mask] = best_within_mask[mask].max(best_within_mask[mask ^ (1 << bit)]);
+            }
+        }
+    }
+
+    Ok(best_per_mask.iter().enumerate()
+        .map(|(mask, score)| score + best_within_mask[full_mask ^ mask])
+        .max()
+        .unwrap_or(0))
+}
+
+// best score opening exactly the valves of each mask, 0 for the unreachable ones
+fn get_best_score_per_mask(time: Score, start_id: ValveId, valves: &[ValveForSolv]) -> Result<Vec<Score>>
+{
+    let useful: Vec<ValveId> = valves.iter().filter(|v| v.def.flow_rate > 0).map(|v| v.def.id).collect();
+    if useful.len() > MAX_USEFUL_VALVES {
+        return Err(Error::Unsupported(format!("{} valves with a flow, at most {}", useful.len(), MAX_USEFUL_VALVES)));
+    }
+
+    let mut best_per_mask = vec![0; 1 << useful.len()];
+    explore(start_id, time, 0, 0, &useful, valves, &mut best_per_mask);
+    Ok(best_per_mask)
+}
+
+fn explore(current: ValveId, time_left: Score, mask: usize, score: Score,
+    useful: &[ValveId], valves: &[ValveForSolv], best_per_mask: &mut [Score])
+{
+    best_per_mask[mask] = best_per_mask[mask].max(score);
+    for (bit, &v_id) in useful.iter().enumerate() {
+        if mask & (1 << bit) != 0 {
+            continue;
+        }
+        let distance = valves[current as usize].distances[v_id as usize];
+        // opening a valve at the last minute releases nothing
+        let time_delta = (1 + distance as Score).min(time_left);
+        if time_delta == time_left {
+            continue;
+        }
+        let time_left = time_left - time_delta;
+        let score = score + valves[v_id as usize].def.flow_rate as Score * time_left;
+        explore(v_id, time_left, mask | (1 << bit), score, useful, valves, best_per_mask);
+    }
+}
 
 fn remap_valves_ids(valves: &mut Vec<Valve>)
 {
@@ -364,4 +418,47 @@ mod parse {
         )?;
         Ok(valves)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+Valve AA has flow rate=0; tunnels lead to valves DD, II, BB
+Valve BB has flow rate=13; tunnels lead to valves CC, AA
+Valve CC has flow rate=2; tunnels lead to valves DD, BB
+Valve DD has flow rate=20; tunnels lead to valves CC, AA, EE
+Valve EE has flow rate=3; tunnels lead to valves FF, DD
+Valve FF has flow rate=0; tunnels lead to valves EE, GG
+Valve GG has flow rate=0; tunnels lead to valves FF, HH
+Valve HH has flow rate=22; tunnel leads to valve GG
+Valve II has flow rate=0; tunnels lead to valves AA, JJ
+Valve JJ has flow rate=21; tunnel leads to valve II
+";
+
+    #[test]
+    fn test_exact() {
+        let (_, valves_for_solv, initial_id) = get_structs_for_solv(&mut EXAMPLE.as_bytes()).unwrap();
+        assert_eq!(best_score_alone(30, initial_id, &valves_for_solv).unwrap(), 1651);
+        assert_eq!(best_score_with_elephant(26, initial_id, &valves_for_solv).unwrap(), 1707);
+        assert_eq!(best_score_alone(1, initial_id, &valves_for_solv).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_graphs() {
+        let (valves, valves_for_solv, initial_id) = get_structs_for_solv(&mut EXAMPLE.as_bytes()).unwrap();
+        let id = |name: &str| valves.iter().find(|v| v.name == name).unwrap().id as usize;
+        assert_eq!(valves_for_solv[initial_id as usize].distances[id("HH")], 5);
+        assert_eq!(valves_for_solv[id("JJ")].distances[id("HH")], 7);
+        assert!(valves_for_solv[id("FF")].distances.is_empty());
+
+        let tunnels = tunnels_to_dot(&valves_for_solv);
+        assert_eq!(tunnels.matches(" -- ").count(), 10);
+        assert!(tunnels.contains("    HH [label=\"HH\\n22\", shape=circle];\n"));
+        let distances = distances_to_dot(&valves_for_solv);
+        assert_eq!(distances.matches(" -- ").count(), 7 * 6 / 2);
+        assert!(distances.contains("    HH -- JJ [label=\"7\"];\n"));
+        assert!(!distances.contains("FF"));
+    }
+}
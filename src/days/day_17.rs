@@ -1,147 +1,240 @@
 use crate::days::internal_common::*;
+use crate::days::cycles;
+use crate::days::image::{Image, RenderTarget};
+use crate::days::simulation::{self, RunOptions, Simulation, StepOutcome};
 use std::fmt;
+use std::collections::VecDeque;
+use std::path::Path;
+use macro_lib::GridCell;
 
 pub fn day_17_part_1<Input>(input: &mut Input) -> Result<()>
 where Input: Read
+{
+    solve_part_1(input, &RunOptions::default()).map(|_| ())
+}
+
+pub fn day_17_part_1_render<Input>(input: &mut Input, target: &RenderTarget) -> Result<()>
+where Input: Read
+{
+    let tower = solve_part_1(input, &RunOptions::default())?;
+    target.save(&tower.grid.to_image())?;
+    Ok(())
+}
+
+pub fn day_17_part_1_simulate<Input>(input: &mut Input, options: &RunOptions) -> Result<()>
+where Input: Read
+{
+    solve_part_1(input, options).map(|_| ())
+}
+
+fn solve_part_1<Input>(input: &mut Input, options: &RunOptions) -> Result<Tower>
+where Input: Read
 {
     let content = get_whole_input_as_string(input)?;
-    let mut move_chars_iter = content.chars().cycle();
-    let stop_after_n_rocks = 2022;
-    let width = 7;
-    let height = stop_after_n_rocks * 4;
-    let mut grid = Grid {
-        data: vec![PointContent::Air; width * height],
-        width,
-        height,
-        reached_y: 0
+    // the whole tower is kept to be drawn
+    let config = ChamberConfig { trim: false, ..ChamberConfig::default() };
+    let mut sim = RockFall {
+        tower: Tower::new(content.trim_end().chars().collect(), config)?,
+        num_rocks_left: 2022,
     };
-    let mut num_rocks = 0;
-    let mut current_shape_id = 0;
-    let shape_defs = get_shape_defs();
-
-    while num_rocks < stop_after_n_rocks {
-        let current_shape = &shape_defs[current_shape_id];
-        let mut pos = Point::from_xy(2, grid.reached_y + 3);
-        assert!(pos.y < grid.height as i32);
-        loop {
-            let move_char = move_chars_iter.next().unwrap();
-            move_if_possible(current_shape, &mut pos, &grid, move_char);
-            if can_fall(current_shape, &pos, &grid) {
-                pos.y -= 1;
-            }
-            else {
-                break;
-            }
-        }
-        put_rocks_in_grid(current_shape, &pos, &mut grid);
-        num_rocks += 1;
-        current_shape_id = (current_shape_id + 1) % shape_defs.len();
-    }
+    simulation::run(&mut sim, options, &mut std::io::stdout().lock())?;
 
-    println!("Reached height {}", &grid.reached_y);
+    println!("Reached height {}", &sim.tower.grid.reached_y);
 
-    Ok(())
+    Ok(sim.tower)
 }
 
 pub fn day_17_part_2<Input>(input: &mut Input) -> Result<()>
 where Input: Read
 {
     let content = get_whole_input_as_string(input)?;
-    let move_chars: Vec<char> = content.chars().collect();
-    let mut current_move_id: usize = 0;
-    let width = 7;
-    let height = 10_000;
-    let mut grid = Grid {
-        data: vec![PointContent::Air; width * height],
-        width,
-        height,
-        reached_y: 0
-    };
-    let mut num_rocks = 0;
-    let mut current_shape_id = 0;
-    let shape_defs = get_shape_defs();
-
-    let mut known_states: Vec<Option<(MoveShape, NumStuff)>> = vec![None; 256];
-    let mut found_period = false;
-    let mut stop_after_n_rocks = 0;
-    let mut periodic_height_diff = 0;
-    let mut predicted_num_periods = 0;
-
-    while !found_period || num_rocks < stop_after_n_rocks {
-        let current_shape = &shape_defs[current_shape_id];
-
-        if grid.reached_y > 0 && !found_period {
-            let current_state = MoveShape {move_id: current_move_id, shape_id: current_shape_id};
-            let current_num_stuff = NumStuff {num_rocks, reached_height: grid.reached_y as usize};
-            let id = grid.highest_row_row_state_id();
-            match known_states[id as usize] {
-                None => {
-                    known_states[id as usize] = Some((current_state, current_num_stuff));
-                },
-                Some((previous_state, previous_num_stuff)) => {
-                    if previous_state == current_state {
-                        found_period = true;
-                        println!("Matching already known state at y={}, num_rocks={}", grid.reached_y, num_rocks);
-                        let period = current_num_stuff.num_rocks - previous_num_stuff.num_rocks;
-                        periodic_height_diff = current_num_stuff.reached_height - previous_num_stuff.reached_height;
-                        // A + N*period + B = I
-                        let terms_i: usize = 1_000_000_000_000;
-                        let terms_a: usize = previous_num_stuff.num_rocks;
-                        let terms_n = (terms_i - terms_a) / period;
-                        let terms_b = terms_i - terms_a - terms_n * period;
-                        stop_after_n_rocks = terms_a + period + terms_b;
-                        predicted_num_periods = terms_n;
-                    }
-                },
-            };
+    let mut tower = Tower::new(content.trim_end().chars().collect(), ChamberConfig::default())?;
+    let stop_after_n_rocks: usize = 1_000_000_000_000;
+
+    let mut heights = vec![0];
+    let cycle = cycles::find_cycle(&mut tower,
+        |tower| {
+            tower.drop_rock();
+            heights.push(tower.grid.reached_y as usize);
+        },
+        Tower::fingerprint,
+        2,
+        100_000
+    ).ok_or(Error::NoSolution)?;
+    println!("Cycle of {} rocks after {} rocks", cycle.length, cycle.start);
+
+    let predicted_height = cycle.extrapolate(&heights, stop_after_n_rocks);
+    println!("predicted_height {}", &predicted_height);
+
+    Ok(())
+}
+
+/// Height of the tower after `num_rocks` rocks pushed by `moves`, a line of '<' and '>'
+pub fn tower_height(moves: &str, config: ChamberConfig, num_rocks: usize) -> Result<i64>
+{
+    let mut tower = Tower::new(moves.trim_end().chars().collect(), config)?;
+    for _ in 0..num_rocks {
+        tower.drop_rock();
+    }
+    Ok(tower.grid.reached_y)
+}
+
+/// Rocks of the puzzle, one shape per block of lines, '#' for a rock and '.' for air
+pub const DEFAULT_SHAPES: &str = "\
+####
+
+.#.
+###
+.#.
+
+..#
+..#
+###
+
+#
+#
+#
+#
+
+##
+##
+";
+
+pub struct ChamberConfig {
+    pub width: usize,
+    /// Dropped in turn
+    pub shapes: Vec<ShapeDef>,
+    /// Forget the rows which no rock can reach anymore, so the memory used stays bounded
+    pub trim: bool,
+}
+
+impl Default for ChamberConfig {
+    fn default() -> Self
+    {
+        Self {
+            width: 7,
+            shapes: ShapeDef::parse_all(DEFAULT_SHAPES).unwrap(),
+            trim: true,
         }
+    }
+}
 
-        let mut pos = Point::from_xy(2, grid.reached_y + 3);
-        assert!(pos.y < grid.height as i32);
+// rows added to the buffer between two attempts to trim it
+const TRIM_INTERVAL: usize = 256;
+
+struct Tower {
+    grid: Grid,
+    shape_defs: Vec<ShapeDef>,
+    current_shape_id: usize,
+    move_chars: Vec<char>,
+    current_move_id: usize,
+    trim: bool,
+    // buffer height after the last trim
+    trimmed_height: usize,
+}
+
+impl Tower {
+    fn new(move_chars: Vec<char>, config: ChamberConfig) -> Result<Self>
+    {
+        if move_chars.is_empty() {
+            return Err(Error::Unsupported(String::from("no move")));
+        }
+        if let Some(i) = move_chars.iter().position(|&c| c != '<' && c != '>') {
+            return Err(Error::new_token(i, 1));
+        }
+        if config.shapes.is_empty() {
+            return Err(Error::Unsupported(String::from("no rock shape")));
+        }
+        if let Some(shape) = config.shapes.iter().find(|shape| shape.width() + 2 > config.width) {
+            return Err(Error::Unsupported(format!("rock {} wide in a chamber {} wide", shape.width(), config.width)));
+        }
+        Ok(Self {
+            grid: Grid {
+                data: Vec::new(),
+                width: config.width,
+                height: 0,
+                offset_y: 0,
+                reached_y: 0
+            },
+            shape_defs: config.shapes,
+            current_shape_id: 0,
+            move_chars,
+            current_move_id: 0,
+            trim: config.trim,
+            trimmed_height: 0,
+        })
+    }
+
+    fn drop_rock(&mut self)
+    {
+        let current_shape = &self.shape_defs[self.current_shape_id];
+        let mut pos = Point::from_xy(2, self.grid.reached_y + 3);
+        // enough room for the shape, and for the row above the tower
+        self.grid.grow_to_height(pos.y + current_shape.height() + 1);
         loop {
-            let move_char = move_chars[current_move_id];
-            current_move_id = (current_move_id + 1) % move_chars.len();
-            move_if_possible(current_shape, &mut pos, &grid, move_char);
-            if can_fall(current_shape, &pos, &grid) {
+            let move_char = self.move_chars[self.current_move_id];
+            self.current_move_id = (self.current_move_id + 1) % self.move_chars.len();
+            move_if_possible(current_shape, &mut pos, &self.grid, move_char);
+            if can_fall(current_shape, &pos, &self.grid) {
                 pos.y -= 1;
             }
             else {
                 break;
             }
         }
-        put_rocks_in_grid(current_shape, &pos, &mut grid);
-        num_rocks += 1;
-        current_shape_id = (current_shape_id + 1) % shape_defs.len();
-    }
+        put_rocks_in_grid(current_shape, &pos, &mut self.grid);
+        self.current_shape_id = (self.current_shape_id + 1) % self.shape_defs.len();
 
-    let predicted_height = grid.reached_y as usize + (predicted_num_periods - 1) * periodic_height_diff;
-    println!("predicted_height {}", &predicted_height);
+        if self.trim && self.grid.height >= self.trimmed_height + TRIM_INTERVAL {
+            self.grid.trim();
+            self.trimmed_height = self.grid.height;
+        }
+    }
 
-    Ok(())
+    // next shape and move, and how deep rocks can go in every column
+    fn fingerprint(&self) -> (usize, usize, Vec<i64>)
+    {
+        let depths = self.grid.lowest_reachable_per_column().iter().map(|y| self.grid.reached_y - y).collect();
+        (self.current_shape_id, self.current_move_id, depths)
+    }
 }
 
-#[derive(Clone, Copy, PartialEq)]
-struct MoveShape {
-    shape_id: usize,
-    move_id: usize,
+// one rock is dropped at each step
+struct RockFall {
+    tower: Tower,
+    num_rocks_left: usize,
 }
 
-#[derive(Clone, Copy)]
-struct NumStuff {
-    num_rocks: usize,
-    reached_height: usize
+impl Simulation for RockFall {
+    fn step(&mut self) -> StepOutcome
+    {
+        self.tower.drop_rock();
+        self.num_rocks_left -= 1;
+        if self.is_done() { StepOutcome::Done } else { StepOutcome::Continue }
+    }
+
+    // only the top of the tower
+    fn render(&self) -> String
+    {
+        self.tower.grid.to_string().lines().take(40).map(|line| format!("|{line}|\n")).collect()
+    }
+
+    fn is_done(&self) -> bool
+    {
+        self.num_rocks_left == 0
+    }
 }
 
 fn can_fall(shape: &ShapeDef, pos: &Point, grid: &Grid) -> bool
 {
-    let mut next_pos = pos.clone();
+    let mut next_pos = *pos;
     next_pos.y -= 1;
     is_possible_pos(shape, &next_pos, grid)
 }
 
 fn move_if_possible(shape: &ShapeDef, pos: &mut Point, grid: &Grid, move_char: char)
 {
-    let mut next_pos = pos.clone();
+    let mut next_pos = *pos;
     next_pos.x += match move_char {
         '>' => 1,
         '<' => -1,
@@ -182,48 +275,80 @@ fn put_rocks_in_grid(shape: &ShapeDef, pos: &Point, grid: &mut Grid)
 
 #[derive(Debug)]
 struct Grid {
+    // rows from `offset_y`, the ones below were trimmed
     data: Vec<PointContent>,
     width: usize,
     height: usize,
-    reached_y: i32
+    offset_y: i64,
+    reached_y: i64
 }
 
 impl Grid {
     fn get_content_at_point(&self, point: &Point) -> Option<PointContent>
     {
-        if !(0..(self.width as i32)).contains(&point.x) || !(0..(self.height as i32)).contains(&point.y) {
+        let y = point.y - self.offset_y;
+        if !(0..(self.width as i64)).contains(&point.x) || !(0..(self.height as i64)).contains(&y) {
             return None;
         }
-        let id = (point.y as usize) * self.width + (point.x as usize);
+        let id = (y as usize) * self.width + (point.x as usize);
         Some(self.data[id])
     }
 
     fn put_content_at_point(&mut self, point: &Point, content: PointContent)
     {
-        let id = (point.y as usize) * self.width + (point.x as usize);
+        let id = ((point.y - self.offset_y) as usize) * self.width + (point.x as usize);
         self.data[id] = content;
     }
 
-    fn highest_row_row_state_id(&self) -> u8
+    // up to the absolute `top_y`, excluded
+    fn grow_to_height(&mut self, top_y: i64)
     {
-        assert!(self.reached_y != 0);
-        let row_start_id = (self.reached_y as usize - 1) * self.width;
-        make_row_state_id(&self.data[row_start_id..(row_start_id + self.width)])
+        let height = (top_y - self.offset_y) as usize;
+        if height > self.height {
+            self.data.resize(height * self.width, PointContent::Air);
+            self.height = height;
+        }
     }
-}
 
-fn make_row_state_id(row_state: &[PointContent]) -> u8
-{
-    let mut id: u8 = 0;
-    assert!(row_state.len() <= 8);
-    for i in 0..row_state.len() {
-        let v = match row_state[i] {
-            PointContent::Air => 0,
-            PointContent::Rock => 1
-        };
-        id |= v << i;
+    // lowest air cell of every column which a rock could get to, moving down or sideways from above the tower
+    fn lowest_reachable_per_column(&self) -> Vec<i64>
+    {
+        let mut lowest = vec![self.reached_y; self.width];
+        let mut seen = vec![false; self.data.len()];
+        let mut queue: VecDeque<Point> = (0..self.width as i64).map(|x| Point::from_xy(x, self.reached_y)).collect();
+        while let Some(p) = queue.pop_front() {
+            if self.get_content_at_point(&p) != Some(PointContent::Air) {
+                continue;
+            }
+            let id = ((p.y - self.offset_y) as usize) * self.width + p.x as usize;
+            if std::mem::replace(&mut seen[id], true) {
+                continue;
+            }
+            lowest[p.x as usize] = lowest[p.x as usize].min(p.y);
+            queue.extend([Point::from_xy(p.x - 1, p.y), Point::from_xy(p.x + 1, p.y), Point::from_xy(p.x, p.y - 1)]);
+        }
+        lowest
+    }
+
+    // forget the rows below the highest one sealed by rocks, a rock can at most rest on it
+    fn trim(&mut self)
+    {
+        let lowest = self.lowest_reachable_per_column().into_iter().min().unwrap_or(self.reached_y);
+        let num_rows = (lowest - 1 - self.offset_y).max(0) as usize;
+        self.data.drain(..(num_rows * self.width));
+        self.height -= num_rows;
+        self.offset_y += num_rows as i64;
+    }
+
+    // the top of the tower is at the top of the image
+    fn to_image(&self) -> Image
+    {
+        let reached_cells = &self.data[..((self.reached_y - self.offset_y) as usize * self.width)];
+        Image::from_grid(reached_cells, self.width, |content| match content {
+            PointContent::Air => [16, 16, 32],
+            PointContent::Rock => [200, 120, 60],
+        }).flipped_vertically()
     }
-    id
 }
 
 impl fmt::Display for Grid {
@@ -231,7 +356,7 @@ impl fmt::Display for Grid {
         for i_y in (0..self.height).rev() {
             let mut line_buffer = String::with_capacity(self.width);
             for i_x in 0..self.width {
-                line_buffer.push(self.data[i_y * self.width + i_x].to_char());
+                line_buffer.push(self.data[i_y * self.width + i_x].into());
             }
             writeln!(f, "{}", line_buffer)?;
         }
@@ -239,77 +364,134 @@ impl fmt::Display for Grid {
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug, GridCell)]
 enum PointContent {
+    #[cell('.')]
     Air,
+    #[cell('#')]
     Rock
 }
 
-impl PointContent {
-    fn to_char(&self) -> char
-    {
-        match self {
-            Self::Air => '.',
-            Self::Rock => '#',
-        }
-    }
+/// Rocks of a shape, relative to the bottom left corner of the shape
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapeDef {
+    rocks: Vec<Point>
 }
 
-impl fmt::Debug for PointContent {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.to_char())
+impl ShapeDef {
+    /// Shapes separated by empty lines, see `DEFAULT_SHAPES`
+    pub fn parse_all(content: &str) -> Result<Vec<ShapeDef>>
+    {
+        let mut shapes = Vec::new();
+        let mut rows: Vec<Vec<PointContent>> = Vec::new();
+        do_for_each_line_of_str(content, |line| {
+            if line.is_empty() {
+                shapes.extend(Self::from_rows(&rows));
+                rows.clear();
+            }
+            else {
+                rows.push(parse_cells(line)?);
+            }
+            Ok(())
+        })?;
+        shapes.extend(Self::from_rows(&rows));
+        Ok(shapes)
     }
-}
 
-struct ShapeDef {
-    rocks: Vec<Point>
-}
+    pub fn load(path: &Path) -> Result<Vec<ShapeDef>>
+    {
+        Self::parse_all(&std::fs::read_to_string(path)?)
+    }
 
-impl ShapeDef {
-    fn from_rocks(rocks: Vec<Point>) -> Self
+    // None without any rock, the first row being the top of the shape
+    fn from_rows(rows: &[Vec<PointContent>]) -> Option<Self>
     {
-        Self {
-            rocks
+        let mut rocks = Vec::new();
+        for (i_y, row) in rows.iter().rev().enumerate() {
+            for (i_x, content) in row.iter().enumerate() {
+                if *content == PointContent::Rock {
+                    rocks.push(Point::from_xy(i_x as i64, i_y as i64));
+                }
+            }
         }
+        let min_x = rocks.iter().map(|p| p.x).min()?;
+        let min_y = rocks.iter().map(|p| p.y).min()?;
+        let corner = Point::from_xy(min_x, min_y);
+        Some(Self {
+            rocks: rocks.iter().map(|p| p - corner).collect()
+        })
     }
-}
 
-fn get_shape_defs() -> Vec<ShapeDef>
-{
-    let mut shape_defs = Vec::new();
-    shape_defs.push(ShapeDef::from_rocks(vec![
-        Point::from_xy(0, 0),
-        Point::from_xy(1, 0),
-        Point::from_xy(2, 0),
-        Point::from_xy(3, 0),
-    ]));
-    shape_defs.push(ShapeDef::from_rocks(vec![
-        Point::from_xy(1, 0),
-        Point::from_xy(0, 1),
-        Point::from_xy(1, 1),
-        Point::from_xy(2, 1),
-        Point::from_xy(1, 2),
-    ]));
-    shape_defs.push(ShapeDef::from_rocks(vec![
-        Point::from_xy(0, 0),
-        Point::from_xy(1, 0),
-        Point::from_xy(2, 0),
-        Point::from_xy(2, 1),
-        Point::from_xy(2, 2),
-    ]));
-    shape_defs.push(ShapeDef::from_rocks(vec![
-        Point::from_xy(0, 0),
-        Point::from_xy(0, 1),
-        Point::from_xy(0, 2),
-        Point::from_xy(0, 3),
-    ]));
-    shape_defs.push(ShapeDef::from_rocks(vec![
-        Point::from_xy(0, 0),
-        Point::from_xy(1, 0),
-        Point::from_xy(0, 1),
-        Point::from_xy(1, 1),
-    ]));
-    shape_defs
+    fn width(&self) -> usize
+    {
+        self.rocks.iter().map(|p| p.x as usize + 1).max().unwrap_or(0)
+    }
+
+    fn height(&self) -> i64
+    {
+        self.rocks.iter().map(|p| p.y + 1).max().unwrap_or(0)
+    }
 }
 
-type Point = crate::days::points::Point2<i32>;
+type Point = crate::days::points::Point2<i64>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXAMPLE: &str = ">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>";
+
+    #[test]
+    fn test_shapes() {
+        let shapes = ShapeDef::parse_all(DEFAULT_SHAPES).unwrap();
+        assert_eq!(shapes.len(), 5);
+        assert_eq!(shapes[2].rocks, [(0, 0), (1, 0), (2, 0), (2, 1), (2, 2)].map(|(x, y)| Point::from_xy(x, y)));
+        assert_eq!((shapes[3].width(), shapes[3].height()), (1, 4));
+        assert_eq!(ShapeDef::parse_all("...\n.##\n\n\n..\n").unwrap(), [ShapeDef { rocks: vec![Point::from_xy(0, 0), Point::from_xy(1, 0)] }]);
+        assert!(ShapeDef::parse_all("#x\n").is_err());
+
+        let path = std::env::temp_dir().join(format!("day_17_shapes_{}.txt", std::process::id()));
+        std::fs::write(&path, DEFAULT_SHAPES).unwrap();
+        let config = ChamberConfig { shapes: ShapeDef::load(&path).unwrap(), ..ChamberConfig::default() };
+        assert_eq!(tower_height(EXAMPLE, config, 2022).unwrap(), 3068);
+        std::fs::remove_file(&path).unwrap();
+        assert!(ShapeDef::load(&path).is_err());
+
+        let config = ChamberConfig { width: 3, ..ChamberConfig::default() };
+        assert!(tower_height(EXAMPLE, config, 1).is_err());
+        assert!(tower_height("<>x", ChamberConfig::default(), 1).is_err());
+        assert!(tower_height("\n", ChamberConfig::default(), 1).is_err());
+    }
+
+    #[test]
+    fn test_custom_chamber() {
+        // pushed to the right wall, the bars stack up in one column
+        let config = ChamberConfig { width: 3, shapes: ShapeDef::parse_all("#\n#\n").unwrap(), trim: true };
+        assert_eq!(tower_height(">\n", config, 10_000).unwrap(), 20_000);
+    }
+
+    #[test]
+    fn test_trimmed_tower() {
+        let mut full = Tower::new(EXAMPLE.chars().collect(), ChamberConfig { trim: false, ..ChamberConfig::default() }).unwrap();
+        let mut trimmed = Tower::new(EXAMPLE.chars().collect(), ChamberConfig::default()).unwrap();
+        for num_rocks in 1..=5000 {
+            full.drop_rock();
+            trimmed.drop_rock();
+            assert_eq!(full.fingerprint(), trimmed.fingerprint());
+            if num_rocks == 2022 {
+                assert_eq!(trimmed.grid.reached_y, 3068);
+            }
+        }
+        assert_eq!(trimmed.grid.reached_y, full.grid.reached_y);
+        assert!(trimmed.grid.offset_y > 0 && trimmed.grid.height < 2 * TRIM_INTERVAL);
+
+        // pushed to opposite sides, two rocks fill a row of a narrower chamber
+        let config = ChamberConfig { width: 6, shapes: ShapeDef::parse_all("####\n\n##\n").unwrap(), trim: true };
+        let mut tower = Tower::new("<<<<>>>>>".chars().collect(), config).unwrap();
+        for _ in 0..3000 {
+            tower.drop_rock();
+        }
+        assert_eq!(tower.grid.reached_y, 1500);
+        assert!(tower.grid.height < 2 * TRIM_INTERVAL);
+    }
+}
@@ -1,213 +1,88 @@
 use crate::days::internal_common::*;
+use crate::days::regions;
+use crate::days::mesh::{Mesh, MeshTarget};
+use std::collections::HashSet;
 
 pub fn day_18_part_1<Input>(input: &mut Input) -> Result<()>
 where Input: Read
 {
     let input = get_whole_input_as_string(input)?;
-    let points = parse::parse_and_collect(&input)?;
-    
-    let (size_x, size_y, size_z) = compute_grid_size(&points);
-
-    let mut grid = Grid {
-        data: vec![0; size_x * size_y * size_z],
-        size_x,
-        size_y,
-        size_z,
-    };
-
-    for point in &points {
-        let content = grid.get_content_at_point(point).unwrap();
-        grid.put_content_at_point(point, content + 6);
-        for point_neigh in point.get_cube_neighbors() {
-            let content = grid.get_content_at_point(&point_neigh);
-            if content.is_none() {
-                continue;
-            }
-            let content = content.unwrap();
-            grid.put_content_at_point(&point_neigh, content - 1);
-        }
-    }
+    let points: HashSet<Point> = parse::parse_and_collect(&input)?.into_iter().collect();
 
-    let mut surface: usize = 0;
-    for &x in &grid.data {
-        if x > 0 {
-            surface += x as usize;
-        }
-    }
-    println!("Surface: {}", surface);
+    println!("Surface: {}", regions::surface_area(&points));
 
     Ok(())
 }
 
-// values for flood fill
-const UNINIT: i8 = -1;
-const LAVA: i8 = 1;
-const WATER: i8 = 2;
-
 pub fn day_18_part_2<Input>(input: &mut Input) -> Result<()>
 where Input: Read
 {
     let input = get_whole_input_as_string(input)?;
-    let points = parse::parse_and_collect(&input)?;
-    
-    let (size_x, size_y, size_z) = compute_grid_size(&points);
-
-    let mut flood_fill_grid = Grid {
-        data: vec![UNINIT; size_x * size_y * size_z],
-        size_x,
-        size_y,
-        size_z,
-    };
-
-    for point in &points {
-        flood_fill_grid.put_content_at_point(point, LAVA);
-    }
+    let points: HashSet<Point> = parse::parse_and_collect(&input)?.into_iter().collect();
 
-    //flood_fill_recurs(&Point::from_xyz(0, 0, 0), &mut flood_fill_grid);
-    flood_fill(&mut flood_fill_grid);
-
-    let mut surface_grid = Grid {
-        data: vec![0; size_x * size_y * size_z],
-        size_x,
-        size_y,
-        size_z,
-    };
-    for point in &points {
-        let content = surface_grid.get_content_at_point(point).unwrap();
-        surface_grid.put_content_at_point(point, content + 6);
-        for point_neigh in point.get_cube_neighbors() {
-            let content = surface_grid.get_content_at_point(&point_neigh);
-            if content.is_none() {
-                continue;
-            }
-            let content = content.unwrap();
-            let flood_fill_value = flood_fill_grid.get_content_at_point(&point_neigh).unwrap();
-            if flood_fill_value != WATER {
-                surface_grid.put_content_at_point(&point_neigh, content - 1);
-            }
-        }
-    }
+    println!("Surface: {}", regions::exterior_surface_area(&points));
 
-    let mut surface: i64 = 0;
-    for &x in &surface_grid.data {
-        surface += x as i64;
-    }
-    println!("Surface: {}", surface);
+    Ok(())
+}
+
+/// The droplet, then every enclosed pocket of air as a separate object
+pub fn day_18_mesh<Input>(input: &mut Input, target: &MeshTarget) -> Result<()>
+where Input: Read
+{
+    let input = get_whole_input_as_string(input)?;
+    let points: HashSet<Point> = parse::parse_and_collect(&input)?.into_iter().collect();
+
+    let meshes = get_meshes(&points, target.exterior_only);
+    target.save(&meshes)?;
+    println!("Exported the droplet and {} pockets", meshes.len() - 1);
 
     Ok(())
 }
 
-// fn flood_fill_recurs(point: &Point, grid: &mut Grid)
-// {
-//     for neigh in point.get_cube_neighbors() {
-//         let neigh_content = grid.get_content_at_point(&neigh);
-//         if neigh_content.is_none() {
-//             continue;
-//         }
-//         let neigh_content = neigh_content.unwrap();
-//         if neigh_content == UNINIT {
-//             grid.put_content_at_point(&neigh, WATER);
-//             flood_fill_recurs(&neigh, grid);
-//         }
-//     }
-// }
-
-fn flood_fill(grid: &mut Grid)
+fn get_meshes(points: &HashSet<Point>, exterior_only: bool) -> Vec<Mesh>
 {
-    let mut to_visit: Vec<Point> = vec![Point::from_xyz(0, 0, 0)];
-    while !to_visit.is_empty() {
-        let point = to_visit.pop().unwrap();
-        for neigh in point.get_cube_neighbors() {
-            let neigh_content = grid.get_content_at_point(&neigh);
-            if neigh_content.is_none() {
-                continue;
-            }
-            let neigh_content = neigh_content.unwrap();
-            if neigh_content == UNINIT {
-                grid.put_content_at_point(&neigh, WATER);
-                to_visit.push(neigh);
+    let exterior = if exterior_only { Some(regions::exterior(points)) } else { None };
+    // sorted, so that the same input always gives the same file
+    let mut sorted_points: Vec<&Point> = points.iter().collect();
+    sorted_points.sort();
+    let mut droplet = Mesh::new("droplet");
+    for p in sorted_points {
+        for n in p.axis_neighbors() {
+            let is_visible = match &exterior {
+                Some(exterior) => exterior.contains(&n),
+                None => !points.contains(&n),
+            };
+            if is_visible {
+                droplet.add_voxel_face(*p, n - p);
             }
         }
     }
-}
 
-fn compute_grid_size(lava_points: &Vec<Point>) -> (usize, usize, usize)
-{
-    let mut max_coord = Point::from_xyz(0, 0, 0);
-    for point in lava_points {
-        if point.x > max_coord.x {
-            max_coord.x = point.x;
-        }
-        if point.y > max_coord.y {
-            max_coord.y = point.y;
-        }
-        if point.z > max_coord.z {
-            max_coord.z = point.z;
+    let mut meshes = vec![droplet];
+    let pockets = regions::enclosed_pockets(points);
+    let mut sorted_pockets: Vec<(usize, Vec<Point>)> = pockets.members.iter()
+        .map(|members| {
+            let mut members = members.clone();
+            members.sort();
+            members
+        })
+        .enumerate()
+        .collect();
+    sorted_pockets.sort_by(|(_, a), (_, b)| a[0].cmp(&b[0]));
+    for (num, (id, members)) in sorted_pockets.iter().enumerate() {
+        let mut pocket = Mesh::new(&format!("pocket_{}", num + 1));
+        for p in members {
+            for n in p.axis_neighbors().filter(|n| pockets.component_of(n) != Some(*id)) {
+                pocket.add_voxel_face(*p, n - p);
+            }
         }
+        meshes.push(pocket);
     }
-
-    // + 1 is enough for containing all the points, but +2 could be useful for floodfill
-    let size_x = max_coord.x as usize + 2;
-    let size_y = max_coord.y as usize + 2;
-    let size_z = max_coord.z as usize + 2;
-
-    (size_x, size_y, size_z)
+    meshes
 }
 
 type Point = crate::days::points::Point3<i32>;
 
-impl Point {
-
-    fn get_cube_neighbors(&self) -> [Self; 6] {
-        [
-            Point::from_xyz(self.x + 1, self.y    , self.z    ),
-            Point::from_xyz(self.x - 1, self.y    , self.z    ),
-            Point::from_xyz(self.x    , self.y + 1, self.z    ),
-            Point::from_xyz(self.x    , self.y - 1, self.z    ),
-            Point::from_xyz(self.x    , self.y    , self.z + 1),
-            Point::from_xyz(self.x    , self.y    , self.z - 1),
-        ]
-    }
-
-}
-
-struct Grid {
-    data: Vec<i8>,
-    size_x: usize,
-    size_y: usize,
-    size_z: usize,
-}
-
-trait Grid3DAccessWithPoint {
-    type Content;
-    fn get_content_at_point(&self, point: &Point) -> Option<Self::Content>;
-    fn put_content_at_point(&mut self, point: &Point, content: Self::Content);
-}
-
-impl Grid3DAccessWithPoint for Grid {
-    type Content = i8;
-
-    fn get_content_at_point(&self, point: &Point) -> Option<Self::Content>
-    {
-        if !(0..(self.size_x as i32)).contains(&point.x) ||
-            !(0..(self.size_y as i32)).contains(&point.y) ||
-            !(0..(self.size_z as i32)).contains(&point.z) {
-            return None;
-        }
-
-        let (x, y, z) = ((point.x as usize), (point.y as usize), (point.z as usize));
-        let id = z * (self.size_x * self.size_y) + y * self.size_x + x;
-        Some(self.data[id])
-    }
-
-    fn put_content_at_point(&mut self, point: &Point, content: Self::Content)
-    {
-        let (x, y, z) = ((point.x as usize), (point.y as usize), (point.z as usize));
-        let id = z * (self.size_x * self.size_y) + y * self.size_x + x;
-        self.data[id] = content;
-    }
-}
-
 mod parse {
     use crate::days::parse::*;
     use crate::days::parse::nom_goes_brrr::*;
@@ -229,4 +104,59 @@ mod parse {
         )?;
         Ok(ret)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::days::mesh::write_obj;
+    use crate::days::points::Point4;
+
+    const EXAMPLE: &str = "2,2,2\n1,2,2\n3,2,2\n2,1,2\n2,3,2\n2,2,1\n2,2,3\n2,2,4\n2,2,6\n1,2,5\n3,2,5\n2,1,5\n2,3,5\n";
+
+    #[test]
+    fn test_example() {
+        let points: HashSet<Point> = parse::parse_and_collect(EXAMPLE).unwrap().into_iter().collect();
+        assert_eq!(regions::surface_area(&points), 64);
+        assert_eq!(regions::exterior_surface_area(&points), 58);
+
+        let meshes = get_meshes(&points, true);
+        let num_triangles: Vec<usize> = meshes.iter().map(|mesh| mesh.triangles.len()).collect();
+        assert_eq!(num_triangles, [58 * 2, 6 * 2]);
+        assert_eq!(get_meshes(&points, false)[0].triangles.len(), 64 * 2);
+    }
+
+    #[test]
+    fn test_mesh_is_deterministic() {
+        // two hollow cubes, the sets iterating in different orders
+        let shells: Vec<Point> = [2, 6].iter()
+            .flat_map(|&x| Point::from_xyz(x, 2, 2).all_neighbors())
+            .collect();
+        let to_obj = |points: HashSet<Point>| {
+            let mut obj = Vec::new();
+            write_obj(&get_meshes(&points, false), &mut obj).unwrap();
+            obj
+        };
+        let obj = to_obj(shells.iter().copied().collect());
+        assert_eq!(to_obj(shells.iter().rev().copied().collect()), obj);
+        assert_eq!(String::from_utf8(obj).unwrap().matches("o pocket_").count(), 2);
+
+        // pockets are numbered from the smallest point
+        let meshes = get_meshes(&shells.into_iter().collect(), false);
+        assert_eq!(meshes[1].name, "pocket_1");
+        assert!(meshes[1].vertices.iter().all(|v| v.x <= 3));
+    }
+
+    #[test]
+    fn test_4d() {
+        let two_cubes = HashSet::from([Point4::from_xyzw(1, 1, 1, 1), Point4::from_xyzw(1, 1, 2, 1)]);
+        assert_eq!(regions::surface_area(&two_cubes), 14);
+        assert_eq!(regions::exterior_surface_area(&two_cubes), 14);
+
+        // a 3x3x3x3 shell with a hollow center
+        let center = Point4::from_xyzw(2, 2, 2, 2);
+        let shell: HashSet<_> = center.all_neighbors().collect();
+        assert_eq!(regions::surface_area(&shell), 3 * 3 * 3 * 8 + 8);
+        assert_eq!(regions::exterior_surface_area(&shell), 3 * 3 * 3 * 8);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::days::internal_common::*;
+use crate::days::branch_and_bound::{self, BranchAndBound};
 
 pub fn day_19_part_1<Input>(input: &mut Input) -> Result<()>
 where Input: Read
@@ -11,15 +12,9 @@ where Input: Read
     for (i, bp) in blueprints.iter().enumerate() {
         let bp_id = i + 1;
         println!("bp id {} {:?}", bp_id, bp);
-        let mut solv = BBSolv::new(bp.clone(), 24);
-        solv.solve();
-        if solv.sol_found {
-            println!("bp solution {}", solv.lower_bound);
-            sum += bp_id * solv.lower_bound as usize;
-        }
-        else {
-            println!("no solution");
-        }
+        let geodes = max_geodes(bp, 24);
+        println!("bp solution {}", geodes);
+        sum += bp_id * geodes as usize;
     }
 
     println!("Sum {}", sum);
@@ -37,15 +32,9 @@ where Input: Read
 
     for bp in blueprints.iter().take(3) {
         println!("bp {:?}", bp);
-        let mut solv = BBSolv::new(bp.clone(), 32);
-        solv.solve();
-        if solv.sol_found {
-            println!("bp solution {}", solv.lower_bound);
-            prod *= solv.lower_bound as usize;
-        }
-        else {
-            println!("no solution");
-        }
+        let geodes = max_geodes(bp, 32);
+        println!("bp solution {}", geodes);
+        prod *= geodes as usize;
     }
 
     println!("Prod {}", prod);
@@ -53,6 +42,13 @@ where Input: Read
     Ok(())
 }
 
+fn max_geodes(blueprint: &Blueprint, final_depth: u8) -> u8
+{
+    let factory = Factory { blueprint: blueprint.clone(), final_depth };
+    let solution = branch_and_bound::solve(&factory, Factory::root(), &branch_and_bound::Params::default());
+    solution.best_score.unwrap_or(0)
+}
+
 #[derive(Clone, Debug)]
 struct Blueprint {
     ore_cost_in_ore: u8,
@@ -63,7 +59,7 @@ struct Blueprint {
     geo_cost_in_obsi: u8,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 struct ResourceList {
     ore: u8,
     clay: u8,
@@ -77,103 +73,71 @@ struct FullResourceList {
     robot: ResourceList
 }
 
-type NodeId = usize;
-
 #[derive(Clone, Debug)]
-struct BBNode {
-    parent: NodeId,
+struct Node {
     depth: u8,
     resource_list: FullResourceList
 }
 
-struct BBSolv {
-    nodes: Vec<BBNode>,
-    todo: Vec<NodeId>,
-    lower_bound: i32,
-    best_node: NodeId,
+struct Factory {
     blueprint: Blueprint,
-    sol_found: bool,
     final_depth: u8
 }
 
-impl BBSolv {
-    const INIT_LOWER_BOUND: i32 = 1;
+impl BranchAndBound for Factory {
+    type Node = Node;
+    type Score = u8;
+    type DominanceKey = (u8, ResourceList);
 
-    fn new(blueprint: Blueprint, final_depth: u8) -> Self
+    fn branch(&self, node: &Node) -> Vec<Node>
     {
-        let mut ret = BBSolv {
-            nodes: Vec::with_capacity(10_000),
-            todo: Vec::new(),
-            lower_bound: BBSolv::INIT_LOWER_BOUND,
-            best_node: 0,
-            blueprint,
-            sol_found: false,
-            final_depth
-        };
+        self.possible_resource_list_next(&node.resource_list)
+            .into_iter()
+            .map(|resource_list| Node { depth: node.depth + 1, resource_list })
+            .collect()
+    }
 
-        let root = BBNode {
-            parent: 0,
-            depth: 0,
-            resource_list: FullResourceList {
-                mineral: ResourceList { ore: 0, clay: 0, obsi: 0, geo: 0 },
-                robot: ResourceList { ore: 1, clay: 0, obsi: 0, geo: 0 }
-            }
-        };
+    fn upper_bound(&self, node: &Node) -> u8
+    {
+        self.compute_some_upper_bound(node)
+    }
 
-        ret.nodes.push(root);
-        ret.todo.push(0);
+    fn is_terminal(&self, node: &Node) -> bool
+    {
+        node.depth == self.final_depth
+    }
 
-        ret
+    fn score(&self, node: &Node) -> u8
+    {
+        node.resource_list.mineral.geo
     }
 
-    fn step(&mut self)
+    // with the same robots at the same time, having more of every mineral can't be worse
+    fn dominance_key(&self, node: &Node) -> Option<(u8, ResourceList)>
     {
-        let node_id = self.todo.pop().unwrap();
-        let node = self.nodes[node_id].clone();
-        let list_next = self.possible_resource_list_next(&node.resource_list);
-        for next in list_next {
-            // BBSolv::print_decision(&node.resource_list, &next);
-            let node = BBNode {
-                parent: node_id,
-                depth: node.depth + 1,
-                resource_list: next
-            };
-            let ub = self.compute_some_upper_bound(&node);
-            if (ub as i32) < self.lower_bound {
-                // println!("SKIP");
-                continue;
-            }
-            if node.depth == self.final_depth {
-                let score = node.resource_list.mineral.geo as i32;
-                // dbg!(&node, score);
-                if score >= self.lower_bound {
-                    self.lower_bound = score;
-                    self.nodes.push(node);
-                    self.best_node = self.nodes.len() - 1;
-                    self.sol_found = true;
-                }
-                continue;
-            }
-            self.nodes.push(node);
-            self.todo.push(self.nodes.len() - 1);
-        }
+        Some((node.depth, node.resource_list.robot))
     }
 
-    fn solve(&mut self)
+    fn dominates(&self, a: &Node, b: &Node) -> bool
     {
-        let print_every_n_steps = 10_000;
-        let mut steps_since_last_print = 0;
-        while !self.todo.is_empty() {
-            self.step();
-            steps_since_last_print += 1;
-            if steps_since_last_print == print_every_n_steps {
-                println!("Processed {} nodes", self.nodes.len());
-                steps_since_last_print = 0;
+        let (a, b) = (&a.resource_list.mineral, &b.resource_list.mineral);
+        a.ore >= b.ore && a.clay >= b.clay && a.obsi >= b.obsi && a.geo >= b.geo
+    }
+}
+
+impl Factory {
+    fn root() -> Node
+    {
+        Node {
+            depth: 0,
+            resource_list: FullResourceList {
+                mineral: ResourceList { ore: 0, clay: 0, obsi: 0, geo: 0 },
+                robot: ResourceList { ore: 1, clay: 0, obsi: 0, geo: 0 }
             }
         }
     }
 
-    fn compute_some_upper_bound(&self, node: &BBNode) -> u8
+    fn compute_some_upper_bound(&self, node: &Node) -> u8
     {
         let mut resource_list = node.resource_list.clone();
         for _i in node.depth..self.final_depth {
@@ -216,19 +180,11 @@ impl BBSolv {
     }
 
     #[allow(dead_code)]
-    fn print_best(&self)
+    fn print_path(path: &[&Node])
     {
-        let mut ancestry = Vec::new();
-        let mut node = &self.nodes[self.best_node];
-        while node.depth != 0 {
-            ancestry.push(node);
-            node = &self.nodes[node.parent];
-        }
-        for i in (0..ancestry.len()).rev() {
-            let node = ancestry[i];
-            println!("Depth {}", node.depth);
-            BBSolv::print_decision(&self.nodes[node.parent].resource_list, &node.resource_list);
-            // dbg!(&node.resource_list);
+        for pair in path.windows(2) {
+            println!("Depth {}", pair[1].depth);
+            Factory::print_decision(&pair[0].resource_list, &pair[1].resource_list);
         }
     }
 
@@ -236,7 +192,7 @@ impl BBSolv {
     {
         let mut ret = Vec::new();
 
-        let current_mineral = current.mineral.clone();
+        let current_mineral = current.mineral;
         let max_add_r_geo = (current_mineral.ore / self.blueprint.geo_cost_in_ore).min(
             current_mineral.obsi / self.blueprint.geo_cost_in_obsi
         );
@@ -371,10 +327,10 @@ mod test {
             obsi_cost_in_clay: 14,
             geo_cost_in_obsi: 7,
         };
-        let mut solv = BBSolv::new(blueprint, 24);
-        solv.solve();
-        solv.print_best();
-        assert_eq!(solv.lower_bound, 9);
+        let factory = Factory { blueprint, final_depth: 24 };
+        let solution = branch_and_bound::solve(&factory, Factory::root(), &branch_and_bound::Params::default());
+        Factory::print_path(&solution.best_path());
+        assert_eq!(solution.best_score, Some(9));
     }
 
     #[test]
@@ -387,9 +343,6 @@ mod test {
             obsi_cost_in_clay: 8,
             geo_cost_in_obsi: 12,
         };
-        let mut solv = BBSolv::new(blueprint, 24);
-        solv.solve();
-        // solv.print_best();
-        assert_eq!(solv.lower_bound, 12);
+        assert_eq!(max_geodes(&blueprint, 24), 12);
     }
 }
\ No newline at end of file
@@ -1,4 +1,6 @@
 use crate::days::internal_common::*;
+use macro_lib::GridCell;
+use crate::days::directions::{Direction4 as Direction, Turn};
 
 pub fn day_22_part_1<Input>(input: &mut Input) -> Result<()>
 where Input: Read
@@ -18,15 +20,15 @@ where Input: Read
     let input = get_whole_input_as_string(input)?;
     
     let (grid_input, moves) = parse::parse(&input)?;
-    let grid = Grid::new(grid_input);
+    let grid = Grid::new(grid_input)?;
 
     let faces = get_face_links(&grid);
 
     let mut current = Point::from_xy(0, 0);
-    let mut current_dir = POINT_RIGHT;
+    let mut current_dir = Direction::Right;
     let mut content = grid.get_content_at_point(&current).unwrap();
     while content != PointContent::Open {
-        current = current + POINT_RIGHT;
+        current += Direction::Right.to_point();
         content = grid.get_content_at_point(&current).unwrap();
     }
     // println!("{:?}", &current);
@@ -59,36 +61,23 @@ where Input: Read
 
     let final_col = current.x + 1;
     let final_row = current.y + 1;
-    let direction_int;
-    if current_dir.x == 1 {
-        direction_int = 0;
-    }
-    else if current_dir.x == -1 {
-        direction_int = 2;
-    }
-    else if current_dir.y == 1 {
-        direction_int = 1;
-    }
-    else {
-        direction_int = 3;
-    }
-    let ans = final_row * 1000 + final_col * 4 + direction_int;
+    let ans = final_row * 1000 + final_col * 4 + current_dir.facing() as i32;
     println!("Ans {}", ans);
 
     Ok(())
 }
 
 fn day_22_common<F>(input: String, get_next_fn: F) -> Result<i32>
-where F: Fn(&Point, &Point, &Grid) -> (Point, PointContent, Point)
+where F: Fn(&Point, &Direction, &Grid) -> (Point, PointContent, Direction)
 {
     let (grid_input, moves) = parse::parse(&input)?;
-    let grid = Grid::new(grid_input);
+    let grid = Grid::new(grid_input)?;
     
     let mut current = Point::from_xy(0, 0);
-    let mut current_dir = POINT_RIGHT;
+    let mut current_dir = Direction::Right;
     let mut content = grid.get_content_at_point(&current).unwrap();
     while content != PointContent::Open {
-        current = current + POINT_RIGHT;
+        current += Direction::Right.to_point();
         content = grid.get_content_at_point(&current).unwrap();
     }
     // println!("{:?}", &current);
@@ -121,26 +110,13 @@ where F: Fn(&Point, &Point, &Grid) -> (Point, PointContent, Point)
 
     let final_col = current.x + 1;
     let final_row = current.y + 1;
-    let direction_int;
-    if current_dir.x == 1 {
-        direction_int = 0;
-    }
-    else if current_dir.x == -1 {
-        direction_int = 2;
-    }
-    else if current_dir.y == 1 {
-        direction_int = 1;
-    }
-    else {
-        direction_int = 3;
-    }
-    let ans = final_row * 1000 + final_col * 4 + direction_int;
+    let ans = final_row * 1000 + final_col * 4 + current_dir.facing() as i32;
     Ok(ans)
 }
 
-fn get_next_part_1(current: &Point, dir: &Point, grid: &Grid) -> (Point, PointContent, Point)
+fn get_next_part_1(current: &Point, dir: &Direction, grid: &Grid) -> (Point, PointContent, Direction)
 {
-    let mut next = current + dir;
+    let mut next = current + dir.to_point();
     let mut next_content = grid.get_content_at_point(&next);
     if next_content.is_none() {
         if next.x == -1 {
@@ -167,10 +143,10 @@ fn get_next_part_1(current: &Point, dir: &Point, grid: &Grid) -> (Point, PointCo
         (next, next_content, _) = get_next_part_1(&next, dir, &grid);
     }
 
-    (next, next_content, dir.clone())
+    (next, next_content, *dir)
 }
 
-fn get_next_part_2(current: &Point, dir: &Point, grid: &Grid, faces: &Vec<Option<Face>>) -> (Point, PointContent, Point)
+fn get_next_part_2(current: &Point, dir: &Direction, grid: &Grid, faces: &Vec<Option<Face>>) -> (Point, PointContent, Direction)
 {
     let cube_side_len = (grid.size_x.max(grid.size_y) / 4) as i32;
     let rot = |p: &Point| {
@@ -191,7 +167,7 @@ fn get_next_part_2(current: &Point, dir: &Point, grid: &Grid, faces: &Vec<Option
         }
     };
 
-    let next = current + dir;
+    let next = current + dir.to_point();
     let next_content = grid.get_content_at_point(&next);
     match next_content {
         None | Some(PointContent::Space) => {
@@ -202,38 +178,39 @@ fn get_next_part_2(current: &Point, dir: &Point, grid: &Grid, faces: &Vec<Option
             let face_id = (face_i_y * grid_size_x_in_faces + face_i_x) as usize;
             let face = faces[face_id].as_ref().unwrap();
             let mut p_rel = Point::from_xy(current.x - face_i_x * cube_side_len, current.y - face_i_y * cube_side_len);
-            let face_link;
-            if dir.x == 1 {
-                face_link = &face.link_right;
-                p_rel.x = 0;
-            }
-            else if dir.x == -1 {
-                face_link = &face.link_left;
-                p_rel.x = cube_side_len - 1;
-            }
-            else if dir.y == 1 {
-                face_link = &face.link_down;
-                p_rel.y = 0;
-            }
-            else {
-                face_link = &face.link_up;
-                p_rel.y = cube_side_len - 1;
-            }
+            let face_link = match dir {
+                Direction::Right => {
+                    p_rel.x = 0;
+                    &face.link_right
+                },
+                Direction::Left => {
+                    p_rel.x = cube_side_len - 1;
+                    &face.link_left
+                },
+                Direction::Down => {
+                    p_rel.y = 0;
+                    &face.link_down
+                },
+                Direction::Up => {
+                    p_rel.y = cube_side_len - 1;
+                    &face.link_up
+                },
+            };
             let next_face_x = (face_link.id as i32 % grid_size_x_in_faces) * cube_side_len;
             let next_face_y = (face_link.id as i32 / grid_size_x_in_faces) * cube_side_len;
             
-            let mut next_dir = dir.clone();
+            let mut next_dir = *dir;
             for _i in 0..face_link.num_rot {
                 p_rel = rot(&p_rel);
-                next_dir = next_dir.turn(TurnDirection::Right);
+                next_dir = next_dir.turn_right();
             }
 
-            let next = Point::from_xy(next_face_x, next_face_y) + &p_rel;
+            let next = Point::from_xy(next_face_x, next_face_y) + p_rel;
             // dbg!(face_id, next_face_x, next_face_y, &p_rel);
             let next_content = grid.get_content_at_point(&next).unwrap();
             return (next, next_content, next_dir)
         },
-        Some(next_content) => (next, next_content, dir.clone())
+        Some(next_content) => (next, next_content, *dir)
     }
 }
 
@@ -253,12 +230,8 @@ struct FaceLink {
 
 type Point = crate::days::points::Point2<i32>;
 type Point3 = crate::days::points::Point3<i32>;
-const POINT_RIGHT: Point = Point {x: 1, y: 0};
-// const POINT_LEFT: Point = Point {x: -1, y: 0};
-// const POINT_UP: Point = Point {x: 0, y: -1};
-// const POINT_DOWN: Point = Point {x: 0, y: 1};
 
-#[derive(Clone)]
+#[derive(Clone, Copy)]
 struct Frame3 {
     x: Point3,
     y: Point3,
@@ -269,56 +242,54 @@ impl Default for Frame3 {
     fn default() -> Frame3
     {
         Frame3 {
-            x: Point3 {x:1, y: 0, z: 0},
-            y: Point3 {x:0, y: 1, z: 0},
-            z: Point3 {x:0, y: 0, z: 1},
+            x: Point3::from_xyz(1, 0, 0),
+            y: Point3::from_xyz(0, 1, 0),
+            z: Point3::from_xyz(0, 0, 1),
+        }
+    }
+}
+
+impl Frame3 {
+    // the axes of the frame are turned like `rotate` turns the axes of the default frame
+    fn rotated<F>(&self, rotate: F) -> Frame3
+    where F: Fn(&Point3) -> Point3
+    {
+        let from_local = |axis: Point3| {
+            let local = rotate(&axis);
+            self.x * local.x + self.y * local.y + self.z * local.z
+        };
+        let default = Frame3::default();
+        Frame3 {
+            x: from_local(default.x),
+            y: from_local(default.y),
+            z: from_local(default.z),
         }
     }
 }
 
 fn rotate_frame_up(frame: &Frame3) -> Frame3
 {
-    Frame3 {
-        x: -frame.z.clone(),
-        y: frame.y.clone(),
-        z: frame.x.clone(),
-    }
+    frame.rotated(|p| p.rotate_y(1))
 }
 
 fn rotate_frame_down(frame: &Frame3) -> Frame3
 {
-    Frame3 {
-        x: frame.z.clone(),
-        y: frame.y.clone(),
-        z: -frame.x.clone(),
-    }
+    frame.rotated(|p| p.rotate_y(-1))
 }
 
 fn rotate_frame_left(frame: &Frame3) -> Frame3
 {
-    Frame3 {
-        x: frame.x.clone(),
-        y: frame.z.clone(),
-        z: -frame.y.clone(),
-    }
+    frame.rotated(|p| p.rotate_x(1))
 }
 
 fn rotate_frame_right(frame: &Frame3) -> Frame3
 {
-    Frame3 {
-        x: frame.x.clone(),
-        y: -frame.z.clone(),
-        z: frame.y.clone(),
-    }
+    frame.rotated(|p| p.rotate_x(-1))
 }
 
 fn rotate_frame_clockwise(frame: &Frame3) -> Frame3
 {
-    Frame3 {
-        x: -frame.y.clone(),
-        y: frame.x.clone(),
-        z: frame.z.clone(),
-    }
+    frame.rotated(|p| p.rotate_z(-1))
 }
 
 fn get_num_rot_clockwise(from: &Frame3, to: &Frame3) -> u8
@@ -327,7 +298,7 @@ fn get_num_rot_clockwise(from: &Frame3, to: &Frame3) -> u8
     if from.x == to.x {
         return 0;
     }
-    let mut t = from.clone();
+    let mut t = *from;
     for i in 1..=3 {
         t = rotate_frame_clockwise(&t);
         if t.x == to.x {
@@ -410,16 +381,6 @@ fn get_face_links(grid: &Grid) -> Vec<Option<Face>>
     faces
 }
 
-impl Point {
-    fn turn(&self, direction: TurnDirection) -> Self
-    {
-        match direction {
-            TurnDirection::Left => Self::from_xy(self.y, -self.x),
-            TurnDirection::Right => Self::from_xy(-self.y, self.x),
-        }
-    }
-}
-
 struct Grid {
     data: Vec<PointContent>,
     size_x: usize,
@@ -427,21 +388,22 @@ struct Grid {
 }
 
 impl Grid {
-    fn new(input: &str) -> Self
+    fn new(input: &str) -> Result<Self>
     {
-        let size_x = input.lines().map(|line| line.len()).max().unwrap();
+        let size_x = input.lines().map(|line| line.len()).max().ok_or(Error::UnexpectedInputEnd)?;
         let size_y = input.lines().count();
         let mut data = vec![PointContent::Space; size_x * size_y];
         let mut offset = 0;
-        for line in input.lines() {
-            for (i, c) in line.chars().enumerate() {
-                data[offset + i] = PointContent::from(c);
+        do_for_each_line_of_str(input, |line| {
+            for (i, content) in parse_cells(line)?.into_iter().enumerate() {
+                data[offset + i] = content;
             }
             offset += size_x;
-        }
-        Self {
+            Ok(())
+        })?;
+        Ok(Self {
             data, size_x, size_y
-        }
+        })
     }
 }
 
@@ -460,29 +422,20 @@ impl Grid {
 
 #[derive(Debug)]
 enum Move {
-    Turn(TurnDirection),
+    Turn(Turn),
     Advance(i32)
 }
 
-#[derive(Debug)]
-enum TurnDirection {
-    Left,
-    Right
-}
-
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, GridCell)]
 enum PointContent {
+    #[cell('.')]
     Open,
+    #[cell('#')]
     Wall,
+    #[cell(' ')]
     Space
 }
 
-impl std::fmt::Display for PointContent {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", char::from(self.clone()))
-    }
-}
-
 impl std::fmt::Display for Grid {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for y in 0..self.size_y {
@@ -495,30 +448,6 @@ impl std::fmt::Display for Grid {
     }
 }
 
-impl std::convert::From<PointContent> for char {
-    fn from(content: PointContent) -> Self
-    {
-        match content {
-            PointContent::Open => '.',
-            PointContent::Wall => '#',
-            PointContent::Space => ' ',
-        }
-    }
-}
-
-impl std::convert::From<char> for PointContent {
-    fn from(c: char) -> Self
-    {
-        match c {
-            '.' => PointContent::Open,
-            '#' => PointContent::Wall,
-            ' ' => PointContent::Space,
-            _ => panic!("Unexpected char")
-        }
-    }
-}
-
-
 mod parse {
     use nom::Parser;
     use nom::bytes::complete::take_until;
@@ -530,8 +459,8 @@ mod parse {
     pub(super) fn parse(input: &str) -> super::Result<(&str, Vec<Move>)>
     {
         let parse_turn_direction = alt((
-            tag("L").map(|_| Move::Turn(TurnDirection::Left)),
-            tag("R").map(|_| Move::Turn(TurnDirection::Right)),
+            tag("L").map(|_| Move::Turn(Turn::Left)),
+            tag("R").map(|_| Move::Turn(Turn::Right)),
         ));
 
         let parse_moves = many0(alt((
@@ -1,58 +1,139 @@
 use crate::days::internal_common::*;
+use macro_lib::GridCell;
 use std::collections::HashMap;
+use crate::days::points::BoundingBox;
+use crate::days::image::{Image, RenderTarget};
+use crate::days::simulation::{self, RunOptions, Simulation, StepOutcome};
+use crate::days::directions::{Direction4 as Direction, Direction8};
 
 pub fn day_23_part_1<Input>(input: &mut Input) -> Result<()>
 where Input: Read
 {
-    let input = get_whole_input_as_string(input)?;
-    let mut grid = Grid::new(&input, 10);
-    
-    let mut rectangle = Rectangle::new(&grid.elves);
+    solve_part_1(input, &RunOptions::default()).map(|_| ())
+}
 
-    for _round in 0..10 {
-        grid.do_round(|new_pos| rectangle.update(&new_pos));
-    }
+pub fn day_23_part_1_render<Input>(input: &mut Input, target: &RenderTarget) -> Result<()>
+where Input: Read
+{
+    let grid = solve_part_1(input, &RunOptions::default())?;
+    target.save(&grid.to_image())?;
+    Ok(())
+}
+
+pub fn day_23_part_1_simulate<Input>(input: &mut Input, options: &RunOptions) -> Result<()>
+where Input: Read
+{
+    solve_part_1(input, options).map(|_| ())
+}
+
+fn solve_part_1<Input>(input: &mut Input, options: &RunOptions) -> Result<Grid>
+where Input: Read
+{
+    let input = get_whole_input_as_string(input)?;
+    let mut sim = Spreading::new(Grid::new(&input, 10)?, Some(10));
+    simulation::run(&mut sim, options, &mut std::io::stdout().lock())?;
 
-    let empty_area = rectangle.area() as usize - grid.elves.len();
+    let rectangle = BoundingBox::from_points(&sim.grid.elves).ok_or(Error::NoSolution)?;
+    let empty_area = rectangle.volume() as usize - sim.grid.elves.len();
     print!("{:?}", empty_area);
 
-    Ok(())
+    Ok(sim.grid)
 }
 
 pub fn day_23_part_2<Input>(input: &mut Input) -> Result<()>
 where Input: Read
+{
+    solve_part_2(input, &RunOptions::default()).map(|_| ())
+}
+
+pub fn day_23_part_2_render<Input>(input: &mut Input, target: &RenderTarget) -> Result<()>
+where Input: Read
+{
+    let grid = solve_part_2(input, &RunOptions::default())?;
+    target.save(&grid.to_image())?;
+    Ok(())
+}
+
+pub fn day_23_part_2_simulate<Input>(input: &mut Input, options: &RunOptions) -> Result<()>
+where Input: Read
+{
+    solve_part_2(input, options).map(|_| ())
+}
+
+fn solve_part_2<Input>(input: &mut Input, options: &RunOptions) -> Result<Grid>
+where Input: Read
 {
     let input = get_whole_input_as_string(input)?;
-    let mut grid = Grid::new(&input, 100);
-    
-    let mut num_rounds = 0;
-    let mut stopped_moving = false;
-
-    while !stopped_moving {
-        num_rounds += 1;
-        stopped_moving = true;
-        grid.do_round(|_| stopped_moving = false);
+    let mut sim = Spreading::new(Grid::new(&input, 100)?, None);
+    simulation::run(&mut sim, options, &mut std::io::stdout().lock())?;
+
+    println!("Num rounds {}", sim.num_rounds);
+
+    Ok(sim.grid)
+}
+
+// one round at each step, until no elf moves or `max_rounds` rounds
+struct Spreading {
+    grid: Grid,
+    num_rounds: usize,
+    max_rounds: Option<usize>,
+    stopped_moving: bool,
+}
+
+impl Spreading {
+    fn new(grid: Grid, max_rounds: Option<usize>) -> Self
+    {
+        Self {
+            grid,
+            num_rounds: 0,
+            max_rounds,
+            stopped_moving: false,
+        }
     }
+}
 
-    println!("Num rounds {}", num_rounds);
+impl Simulation for Spreading {
+    fn step(&mut self) -> StepOutcome
+    {
+        self.num_rounds += 1;
+        let mut stopped_moving = true;
+        self.grid.do_round(|_| stopped_moving = false);
+        self.stopped_moving = stopped_moving;
+        if self.is_done() { StepOutcome::Done } else { StepOutcome::Continue }
+    }
 
-    Ok(())
+    // cropped to the elves
+    fn render(&self) -> String
+    {
+        let mut ret = String::new();
+        if let Some(bounding_box) = BoundingBox::from_points(&self.grid.elves) {
+            for y in bounding_box.min.y..=bounding_box.max.y {
+                for x in bounding_box.min.x..=bounding_box.max.x {
+                    let content = self.grid.get_content_at_point(&Point::from_xy(x, y)).unwrap();
+                    ret.push(content.into());
+                }
+                ret.push('\n');
+            }
+        }
+        ret
+    }
+
+    fn is_done(&self) -> bool
+    {
+        self.stopped_moving || self.max_rounds.is_some_and(|max_rounds| self.num_rounds >= max_rounds)
+    }
 }
 
 type Point = crate::days::points::Point2<i32>;
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, GridCell)]
 enum PointContent {
+    #[cell('.')]
     Empty,
+    #[cell('#')]
     Elf,
 }
 
-impl std::fmt::Display for PointContent {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", char::from(self.clone()))
-    }
-}
-
 impl std::fmt::Display for Grid {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for y in 0..self.size_y {
@@ -65,27 +146,6 @@ impl std::fmt::Display for Grid {
     }
 }
 
-impl std::convert::From<PointContent> for char {
-    fn from(content: PointContent) -> Self
-    {
-        match content {
-            PointContent::Empty => '.',
-            PointContent::Elf => '#',
-        }
-    }
-}
-
-impl std::convert::From<char> for PointContent {
-    fn from(c: char) -> Self
-    {
-        match c {
-            '.' => PointContent::Empty,
-            '#' => PointContent::Elf,
-            _ => panic!("Unexpected char")
-        }
-    }
-}
-
 struct Grid {
     data: Vec<PointContent>,
     size_x: usize,
@@ -95,18 +155,17 @@ struct Grid {
 }
 
 impl Grid {
-    fn new(input: &str, num_rounds: usize) -> Self
+    fn new(input: &str, num_rounds: usize) -> Result<Self>
     {
-        let unpad_size_x = input.lines().map(|line| line.len()).max().unwrap();
+        let unpad_size_x = input.lines().map(|line| line.len()).max().ok_or(Error::UnexpectedInputEnd)?;
         let unpad_size_y = input.lines().count();
         let size_x = unpad_size_x + num_rounds * 2;
         let size_y = unpad_size_y + num_rounds * 2;
         let mut elves = Vec::new();
         let mut data = vec![PointContent::Empty; size_x * size_y];
         let mut offset = num_rounds * size_x + num_rounds;
-        for line in input.lines() {
-            for (i, c) in line.chars().enumerate() {
-                let content = PointContent::from(c);
+        do_for_each_line_of_str(input, |line| {
+            for (i, content) in parse_cells(line)?.into_iter().enumerate() {
                 if content == PointContent::Elf {
                     let x = (num_rounds + i) as i32;
                     let y = (offset / size_x) as i32;
@@ -115,10 +174,11 @@ impl Grid {
                 data[offset + i] = content;
             }
             offset += size_x;
-        }
-        Self {
+            Ok(())
+        })?;
+        Ok(Self {
             data, size_x, size_y, elves, start_dir_i: 0
-        }
+        })
     }
 }
 
@@ -135,67 +195,31 @@ impl Grid {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
-enum Direction {
-    Top,
-    Bottom,
-    Left,
-    Right
-}
-
-impl Point {
-    fn get_neigh(&self, dir: &Direction) -> Point
+impl Grid {
+    // the 3 positions on the side of `pos` facing `dir` must be free of elves
+    fn is_neigh_available(&self, pos: &Point, dir: &Direction) -> bool
     {
-        match dir {
-            Direction::Top => self + Point::from_xy(0, -1),
-            Direction::Bottom => self + Point::from_xy(0, 1),
-            Direction::Left => self + Point::from_xy(-1, 0),
-            Direction::Right => self + Point::from_xy(1, 0),
-        }
+        let heading = Direction8::from(*dir);
+        [heading.turn_left(), heading, heading.turn_right()].iter().all(
+            |heading| self.get_content_at_point(&(pos + heading.to_point())).unwrap() != PointContent::Elf
+        )
     }
-}
 
-impl Grid {
-    fn is_neigh_available(&self, pos: &Point, dir: &Direction) -> bool
+    // cropped to the elves
+    fn to_image(&self) -> Image
     {
-        match dir {
-            Direction::Top => {
-                let neigh = pos + Point::from_xy(0, -1);
-                for d_x in -1..=1 {
-                    if self.get_content_at_point(&Point::from_xy(neigh.x + d_x, neigh.y)).unwrap() == PointContent::Elf {
-                        return false;
-                    }
-                }
-                true
-            },
-            Direction::Bottom => {
-                let neigh = pos + Point::from_xy(0, 1);
-                for d_x in -1..=1 {
-                    if self.get_content_at_point(&Point::from_xy(neigh.x + d_x, neigh.y)).unwrap() == PointContent::Elf {
-                        return false;
-                    }
-                }
-                true
-            },
-            Direction::Left => {
-                let neigh = pos + Point::from_xy(-1, 0);
-                for d_y in -1..=1 {
-                    if self.get_content_at_point(&Point::from_xy(neigh.x, neigh.y + d_y)).unwrap() == PointContent::Elf {
-                        return false;
-                    }
-                }
-                true
-            },
-            Direction::Right => {
-                let neigh = pos + Point::from_xy(1, 0);
-                for d_y in -1..=1 {
-                    if self.get_content_at_point(&Point::from_xy(neigh.x, neigh.y + d_y)).unwrap() == PointContent::Elf {
-                        return false;
-                    }
-                }
-                true
-            },
-        }
+        let bounding_box = match BoundingBox::from_points(&self.elves) {
+            Some(bounding_box) => bounding_box,
+            None => return Image::new(0, 0),
+        };
+        let size = bounding_box.size();
+        Image::from_fn(size.x as usize, size.y as usize, |x, y| {
+            let pos = bounding_box.min + Point::from_xy(x as i32, y as i32);
+            match self.get_content_at_point(&pos) {
+                Some(PointContent::Elf) => [60, 200, 80],
+                _ => [16, 16, 32],
+            }
+        })
     }
 
     fn get_pos_id(&self, pos: &Point) -> i32
@@ -208,7 +232,7 @@ impl Grid {
         let num_elves = self.elves.len();
         let mut used_positions: HashMap<i32, usize> = HashMap::with_capacity(num_elves);
         let mut next_positions = self.elves.clone();
-        let directions = &[Direction::Top, Direction::Bottom, Direction::Left, Direction::Right];
+        let directions = &[Direction::Up, Direction::Down, Direction::Left, Direction::Right];
 
         for elf_i in 0..num_elves {
             let pos = &self.elves[elf_i];
@@ -220,7 +244,7 @@ impl Grid {
             for dir_i in 0..4 {
                 let dir = directions[(self.start_dir_i + dir_i) % 4];
                 if self.is_neigh_available(pos, &dir) {
-                    let next_pos = pos.get_neigh(&dir);
+                    let next_pos = pos + dir.to_point();
                     used_positions.entry(self.get_pos_id(&next_pos))
                         .and_modify(|x| *x += 1)
                         .or_insert(1);
@@ -239,8 +263,8 @@ impl Grid {
             if *used_positions.get(&self.get_pos_id(next_pos)).unwrap() < 2 {
                 self.put_content_at_point(pos, PointContent::Empty);
                 self.put_content_at_point(next_pos, PointContent::Elf);
-                self.elves[elf_i] = next_pos.clone();
-                callback(next_pos.clone());
+                self.elves[elf_i] = *next_pos;
+                callback(*next_pos);
             }
         }
 
@@ -248,39 +272,3 @@ impl Grid {
         self.start_dir_i = (self.start_dir_i + 1) % 4;
     }
 }
-
-#[derive(Debug)]
-struct Rectangle {
-    min_x: i32,
-    max_x: i32,
-    min_y: i32,
-    max_y: i32,
-}
-
-impl Rectangle {
-    fn new(positions: &Vec<Point>) -> Self {
-        let mut ret = Self {
-            min_x: i32::MAX,
-            max_x: i32::MIN,
-            min_y: i32::MAX,
-            max_y: i32::MIN,
-        };
-        for pos in positions {
-            ret.update(pos);
-        }
-        ret
-    }
-
-    fn update(&mut self, pos: &Point)
-    {
-        self.min_x = self.min_x.min(pos.x);
-        self.max_x = self.max_x.max(pos.x);
-        self.min_y = self.min_y.min(pos.y);
-        self.max_y = self.max_y.max(pos.y);
-    }
-
-    fn area(&self) -> i32
-    {
-        (self.max_x - self.min_x + 1) * (self.max_y - self.min_y + 1)
-    }
-}
\ No newline at end of file
@@ -1,15 +1,29 @@
 use crate::days::internal_common::*;
-use std::collections::BTreeSet;
+use macro_lib::GridCell;
+use crate::days::directions::Direction4 as Direction;
+use crate::days::search;
+use crate::days::image::{Image, RenderTarget, WHITE};
+use crate::days::simulation::{self, RunOptions, Simulation, StepOutcome};
+use std::collections::HashSet;
 
 pub fn day_24_part_1<Input>(input: &mut Input) -> Result<()>
 where Input: Read
 {
     let input = get_whole_input_as_string(input)?;
-    let grid = RawGrid::new(&input);
+    let grid = RawGrid::new(&input)?;
     let grid = Grid::new(grid);
 
-    grid.solve_dfs();
+    grid.solve().map(|_| ())
+}
+
+pub fn day_24_part_1_render<Input>(input: &mut Input, target: &RenderTarget) -> Result<()>
+where Input: Read
+{
+    let input = get_whole_input_as_string(input)?;
+    let grid = Grid::new(RawGrid::new(&input)?);
 
+    let time = grid.solve()?;
+    target.save(&grid.to_image(time))?;
     Ok(())
 }
 
@@ -17,16 +31,128 @@ pub fn day_24_part_2<Input>(input: &mut Input) -> Result<()>
 where Input: Read
 {
     let input = get_whole_input_as_string(input)?;
-    let grid = RawGrid::new(&input);
+    let grid = RawGrid::new(&input)?;
     let grid = Grid::new(grid);
 
-    grid.solve_dfs_part_2();
+    grid.solve_part_2().map(|_| ())
+}
+
+pub fn day_24_part_2_render<Input>(input: &mut Input, target: &RenderTarget) -> Result<()>
+where Input: Read
+{
+    let input = get_whole_input_as_string(input)?;
+    let grid = Grid::new(RawGrid::new(&input)?);
+
+    let time = grid.solve_part_2()?;
+    target.save(&grid.to_image(time))?;
+    Ok(())
+}
+
+pub fn day_24_part_1_simulate<Input>(input: &mut Input, options: &RunOptions) -> Result<()>
+where Input: Read
+{
+    simulate(input, 1, options)
+}
+
+pub fn day_24_part_2_simulate<Input>(input: &mut Input, options: &RunOptions) -> Result<()>
+where Input: Read
+{
+    simulate(input, 3, options)
+}
+
+fn simulate<Input>(input: &mut Input, num_trips: usize, options: &RunOptions) -> Result<()>
+where Input: Read
+{
+    let input = get_whole_input_as_string(input)?;
+    let grid = Grid::new(RawGrid::new(&input)?);
 
+    let mut sim = Expedition::new(&grid, num_trips);
+    simulation::run(&mut sim, options, &mut std::io::stdout().lock())?;
+    if sim.positions.is_empty() {
+        return Err(Error::NoSolution);
+    }
+    println!("Done {} trips at time {}", num_trips - sim.targets.len(), sim.time);
     Ok(())
 }
 
 type Point = crate::days::points::Point2<i32>;
 
+// every position the expedition can be at, one minute per step
+struct Expedition<'a> {
+    grid: &'a Grid,
+    time: usize,
+    positions: HashSet<Point>,
+    // next target last
+    targets: Vec<Point>,
+}
+
+impl<'a> Expedition<'a> {
+    fn new(grid: &'a Grid, num_trips: usize) -> Self
+    {
+        let targets = (0..num_trips).rev()
+            .map(|trip| if trip % 2 == 0 { grid.end } else { grid.start })
+            .collect();
+        Self {
+            grid,
+            time: 0,
+            positions: HashSet::from([grid.start]),
+            targets,
+        }
+    }
+}
+
+impl Simulation for Expedition<'_> {
+    fn step(&mut self) -> StepOutcome
+    {
+        self.positions = self.positions.iter()
+            .flat_map(|p| self.grid.get_next_positions(self.time, p))
+            .collect();
+        self.time += 1;
+
+        if let Some(target) = self.targets.last() {
+            if self.positions.contains(target) {
+                // no need to keep the positions which are late for the next trip
+                self.positions = HashSet::from([*target]);
+                self.targets.pop();
+            }
+        }
+        if self.is_done() { StepOutcome::Done } else { StepOutcome::Continue }
+    }
+
+    // 'E' for the expedition, '*' for a blizzard and '2' for two crossing
+    fn render(&self) -> String
+    {
+        let mut ret = String::new();
+        for y in 0..self.grid.size_y {
+            for x in 0..self.grid.size_x {
+                let p = Point::from_xy(x as i32, y as i32);
+                let c = match &self.grid.data[y * self.grid.size_x + x] {
+                    _ if self.positions.contains(&p) => 'E',
+                    _ if p == self.grid.start || p == self.grid.end => '.',
+                    None => '#',
+                    Some(info) => {
+                        let wind_x = info.wind_pattern_x[self.time % info.wind_pattern_x.len()];
+                        let wind_y = info.wind_pattern_y[self.time % info.wind_pattern_y.len()];
+                        match (wind_x, wind_y) {
+                            (true, true) => '2',
+                            (true, false) | (false, true) => '*',
+                            (false, false) => '.',
+                        }
+                    },
+                };
+                ret.push(c);
+            }
+            ret.push('\n');
+        }
+        ret
+    }
+
+    fn is_done(&self) -> bool
+    {
+        self.targets.is_empty() || self.positions.is_empty()
+    }
+}
+
 struct RawGrid {
     data: Vec<PointContent>,
     size_x: usize,
@@ -53,38 +179,52 @@ struct PointInfo {
 }
 
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, GridCell)]
 enum PointContent {
+    #[cell('#')]
     Wall,
+    #[cell('.')]
     Open,
-    Wind(Direction),
+    #[cell('>')]
+    WindRight,
+    #[cell('v')]
+    WindDown,
+    #[cell('<')]
+    WindLeft,
+    #[cell('^')]
+    WindUp,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Direction {
-    Top,
-    Bottom,
-    Left,
-    Right
+impl PointContent {
+    fn wind(&self) -> Option<Direction>
+    {
+        match self {
+            Self::WindRight => Some(Direction::Right),
+            Self::WindDown => Some(Direction::Down),
+            Self::WindLeft => Some(Direction::Left),
+            Self::WindUp => Some(Direction::Up),
+            Self::Wall | Self::Open => None,
+        }
+    }
 }
 
 impl RawGrid {
-    fn new(input: &str) -> Self
+    fn new(input: &str) -> Result<Self>
     {
-        let size_x = input.lines().map(|line| line.len()).max().unwrap();
+        let size_x = input.lines().map(|line| line.len()).max().ok_or(Error::UnexpectedInputEnd)?;
         let size_y = input.lines().count();
         let mut data = vec![PointContent::Open; size_x * size_y];
         let mut offset = 0;
-        for line in input.lines() {
-            assert!(!line.is_empty());
-            for (i, c) in line.chars().enumerate() {
-                data[offset + i] = PointContent::from(c);
+        do_for_each_line_of_str(input, |line| {
+            for (i, content) in parse_cells(line)?.into_iter().enumerate() {
+                data[offset + i] = content;
             }
             offset += size_x;
-        }
-        Self {
+            Ok(())
+        })?;
+        Ok(Self {
             data, size_x, size_y
-        }
+        })
     }
 
     fn calc_patterns(&self, x_fixed: usize, y_fixed: usize) -> PointInfo
@@ -95,8 +235,8 @@ impl RawGrid {
         let mut wind_pattern_x = vec![false; size_x_between_walls];
         for x in 1..(self.size_x - 1) {
             let id = y_fixed * self.size_x + x;
-            match &self.data[id] {
-                PointContent::Wind(Direction::Left) => {
+            match self.data[id].wind() {
+                Some(Direction::Left) => {
                     if x == x_fixed {
                         wind_pattern_x[0] = true;
                     }
@@ -107,7 +247,7 @@ impl RawGrid {
                         wind_pattern_x[x - x_fixed] = true;
                     }
                 },
-                PointContent::Wind(Direction::Right) => {
+                Some(Direction::Right) => {
                     if x == x_fixed {
                         wind_pattern_x[0] = true;
                     }
@@ -125,8 +265,8 @@ impl RawGrid {
         let mut wind_pattern_y = vec![false; size_y_between_walls];
         for y in 1..(self.size_y - 1) {
             let id = y * self.size_x + x_fixed;
-            match &self.data[id] {
-                PointContent::Wind(Direction::Top) => {
+            match self.data[id].wind() {
+                Some(Direction::Up) => {
                     if y == y_fixed {
                         wind_pattern_y[0] = true;
                     }
@@ -137,7 +277,7 @@ impl RawGrid {
                         wind_pattern_y[y - y_fixed] = true;
                     }
                 },
-                PointContent::Wind(Direction::Bottom) => {
+                Some(Direction::Down) => {
                     if y == y_fixed {
                         wind_pattern_y[0] = true;
                     }
@@ -194,74 +334,80 @@ impl Grid {
         }
     }
 
-    fn solve_dfs(&self)
+    // blizzards are back to their initial positions after this many minutes
+    fn get_period(&self) -> usize
     {
-        let mut points_todo: BTreeSet<Point> = BTreeSet::new();
-        points_todo.insert(self.start.clone());
-        let mut time = 0;
-        while !points_todo.is_empty() {
-            let mut points_todo_next: BTreeSet<Point> = BTreeSet::new();
-            for p in &points_todo {
-                if p == &self.end {
-                    println!("Reached end at time {}", time);
-                    return;
-                }
-                self.solve_dfs_add_todo_next(time, p, &mut points_todo_next);
-            }
-            points_todo = points_todo_next;
-            time += 1;
-        }
+        lcm(self.size_x - 2, self.size_y - 2)
     }
 
-    fn solve_dfs_part_2(&self)
+    // earliest time at which `to` is reached, leaving `from` at `start_time`
+    fn find_earliest_arrival(&self, from: Point, to: Point, start_time: usize) -> Result<usize>
     {
-        let mut points_todo: BTreeSet<Point> = BTreeSet::new();
-        points_todo.insert(self.start.clone());
-        let mut time = 0;
-        let mut trip = 0;
-        while !points_todo.is_empty() {
-            let mut points_todo_next: BTreeSet<Point> = BTreeSet::new();
-            if (trip == 0 || trip == 2) && points_todo.contains(&self.end) {
-                println!("Reached end at time {}", time);
-                if trip == 2 {
-                    return;
-                }
-                points_todo_next.insert(self.end.clone());
-                trip += 1;
-            }
-            else if trip == 1 && points_todo.contains(&self.start) {
-                println!("Reached start at time {}", time);
-                points_todo_next.insert(self.start.clone());
-                trip += 1;
-            }
-            else {
-                for p in &points_todo {
-                    self.solve_dfs_add_todo_next(time, p, &mut points_todo_next);
-                }
+        let period = self.get_period();
+        let res = search::bfs(
+            [(from, start_time % period)],
+            |&(p, time)| self.get_next_positions(time, &p).into_iter()
+                .map(|next| (next, (time + 1) % period))
+                .collect::<Vec<_>>(),
+            |(p, _)| *p == to
+        );
+        res.goal_cost().map(|cost| start_time + cost).ok_or(Error::NoSolution)
+    }
+
+    // returns the arrival time
+    fn solve(&self) -> Result<usize>
+    {
+        let time = self.find_earliest_arrival(self.start, self.end, 0)?;
+        println!("Reached end at time {}", time);
+        Ok(time)
+    }
+
+    fn solve_part_2(&self) -> Result<usize>
+    {
+        let time = self.find_earliest_arrival(self.start, self.end, 0)?;
+        println!("Reached end at time {}", time);
+        let time = self.find_earliest_arrival(self.end, self.start, time)?;
+        println!("Reached start at time {}", time);
+        let time = self.find_earliest_arrival(self.start, self.end, time)?;
+        println!("Reached end at time {}", time);
+        Ok(time)
+    }
+
+    // basin with the blizzards at `time`
+    fn to_image(&self, time: usize) -> Image
+    {
+        Image::from_fn(self.size_x, self.size_y, |x, y| {
+            let p = Point::from_xy(x as i32, y as i32);
+            match &self.data[y * self.size_x + x] {
+                _ if p == self.start || p == self.end => [60, 200, 80],
+                None => [90, 90, 90],
+                Some(info) => {
+                    let wind_x = info.wind_pattern_x[time % info.wind_pattern_x.len()];
+                    let wind_y = info.wind_pattern_y[time % info.wind_pattern_y.len()];
+                    match (wind_x, wind_y) {
+                        (true, true) => WHITE,
+                        (true, false) | (false, true) => [120, 170, 255],
+                        (false, false) => [16, 16, 32],
+                    }
+                },
             }
-            points_todo = points_todo_next;
-            time += 1;
-        }
+        })
     }
 
-    fn solve_dfs_add_todo_next(&self, time: usize, p: &Point, todo_next: &mut BTreeSet<Point>)
+    fn get_next_positions(&self, time: usize, p: &Point) -> Vec<Point>
     {
-        let neighs = [
-            p + Point::from_xy(0, -1),
-            p + Point::from_xy(-1, 0),
-            p.clone(),
-            p + Point::from_xy(1, 0),
-            p + Point::from_xy(0, 1),
-        ];
-        for neigh in &neighs {
-            let content = self.get_ref_content_at_point(neigh);
+        let mut next_positions = Vec::new();
+        // moving in any direction, or waiting
+        let neighs = Direction::ALL.iter().map(|dir| p + dir.to_point()).chain([*p]);
+        for neigh in neighs {
+            let content = self.get_ref_content_at_point(&neigh);
             if content.is_none() {
                 continue;
             }
             let content = content.unwrap();
             if content.is_none() {
-                if neigh == &self.start || neigh == &self.end {
-                    todo_next.insert(neigh.clone());
+                if neigh == self.start || neigh == self.end {
+                    next_positions.push(neigh);
                 }
                 continue;
             }
@@ -269,23 +415,9 @@ impl Grid {
             let no_wind = !info.wind_pattern_x[(time + 1) % info.wind_pattern_x.len()] &&
                 !info.wind_pattern_y[(time + 1) % info.wind_pattern_y.len()];
             if no_wind {
-                todo_next.insert(neigh.clone());
+                next_positions.push(neigh);
             }
         }
-    }
-}
-
-impl std::convert::From<char> for PointContent {
-    fn from(c: char) -> Self
-    {
-        match c {
-            '.' => PointContent::Open,
-            '#' => PointContent::Wall,
-            '>' => PointContent::Wind(Direction::Right),
-            '<' => PointContent::Wind(Direction::Left),
-            '^' => PointContent::Wind(Direction::Top),
-            'v' => PointContent::Wind(Direction::Bottom),
-            _ => panic!("Unexpected char")
-        }
+        next_positions
     }
 }
@@ -1,18 +1,5 @@
 use crate::days::internal_common::*;
-
-fn range_contains_range(
-    first_range: &std::ops::RangeInclusive<u32>,
-    second_range: &std::ops::RangeInclusive<u32>
-) -> bool {
-    first_range.contains(second_range.start()) && first_range.contains(second_range.end())
-}
-
-fn range_overlaps_range(
-    first_range: &std::ops::RangeInclusive<u32>,
-    second_range: &std::ops::RangeInclusive<u32>
-) -> bool {
-    first_range.contains(second_range.start()) || second_range.contains(first_range.start())
-}
+use crate::days::ranges::RangeSet;
 
 pub fn day_4_part_1<Input>(input: &mut Input) -> Result<()>
 where Input: Read
@@ -20,7 +7,9 @@ where Input: Read
     let mut sum = 0;
     do_for_each_line(input, |line| {
         let (first_range, second_range) = parse::parse_line(line)?;
-        if range_contains_range(&first_range, &second_range)|| range_contains_range(&second_range, &first_range) {
+        let first_set = RangeSet::from(first_range.clone());
+        let second_set = RangeSet::from(second_range.clone());
+        if first_set.contains_range(&second_range) || second_set.contains_range(&first_range) {
             sum += 1;
         }
         Ok(())
@@ -35,7 +24,7 @@ where Input: Read
     let mut sum = 0;
     do_for_each_line(input, |line| {
         let (first_range, second_range) = parse::parse_line(line)?;
-        if range_overlaps_range(&first_range, &second_range) {
+        if RangeSet::from(first_range).overlaps(&second_range) {
             sum += 1;
         }
         Ok(())
@@ -47,21 +36,21 @@ where Input: Read
 mod parse {
 
     use crate::days::parse::*;
-    use std::ops::RangeInclusive;
+    use std::ops::Range;
     use nom::{
         IResult,
         bytes::complete::tag,
         sequence::tuple,
     };
 
-    pub fn parse_line(line: &str) -> super::Result<(RangeInclusive<u32>, RangeInclusive<u32>)>
+    pub fn parse_line(line: &str) -> super::Result<(Range<u32>, Range<u32>)>
     {
         parse_line_internal(line)
             .map(|(_, r)| r)
             .map_err(|_| super::Error::new_token(0, line.len()))
     }
 
-    fn parse_line_internal(input: &str) -> IResult<&str, (RangeInclusive<u32>, RangeInclusive<u32>)>
+    fn parse_line_internal(input: &str) -> IResult<&str, (Range<u32>, Range<u32>)>
     {
         let (input, first_range) = parse_range(input)?;
         let (input, _) = tag::<&str, &str, nom::error::Error<&str>>(",")(input)?;
@@ -70,9 +59,9 @@ mod parse {
         Ok((input, (first_range, second_range)))
     }
 
-    pub fn parse_range<'a>(i: &'a str) -> IResult<&'a str, RangeInclusive<u32>> {
+    pub fn parse_range(i: &str) -> IResult<&str, Range<u32>> {
         tuple((parse_int, tag("-"), parse_int))(i)
-            .and_then(|(i, (a, _, b))| Ok((i, a..=b)))
+            .map(|(i, (a, _, b)): (_, (u32, _, u32))| (i, a..(b + 1)))
     }
 
 }
\ No newline at end of file
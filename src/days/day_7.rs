@@ -1,251 +1,370 @@
 use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::RangeBounds;
 use crate::days::internal_common::*;
 
-#[derive(Debug)]
-enum FileNode {
-    Dir(FileNodeDir),
-    File(FileNodeFile)
+pub fn day_7_part_1<Input>(input: &mut Input) -> Result<()>
+where Input: Read
+{
+    let content = get_whole_input_as_string(input)?;
+    let fs = FileSystem::from_terminal_log(&content)?;
+
+    let sum: usize = fs.dirs_with_size_in(..=100000).map(|inode| fs.size(inode)).sum();
+    println!("Sum is {}", sum);
+
+    Ok(())
 }
 
-impl FileNode {
-    fn is_dir(&self) -> bool
-    {
-        match self {
-            Self::Dir(_) => true,
-            _ => false,
-        }
-    }
+pub fn day_7_part_2<Input>(input: &mut Input) -> Result<()>
+where Input: Read
+{
+    let content = get_whole_input_as_string(input)?;
+    let fs = FileSystem::from_terminal_log(&content)?;
+
+    let used = fs.size(fs.root());
+    let free = 70000000_usize.checked_sub(used)
+        .ok_or_else(|| Error::Unsupported(format!("{} used on a disk of 70000000", used)))?;
+    let needed = 30000000_usize.saturating_sub(free);
+    let answer = fs.smallest_dir_freeing(needed).ok_or(Error::NoSolution)?;
+
+    println!("Answer is {}", fs.size(answer));
+
+    Ok(())
 }
 
-type INode = usize;
+pub type INode = usize;
 
-#[derive(Debug, Default)]
-struct FileNodeDir {
-    children: BTreeMap<String, INode>
+#[derive(Debug)]
+enum NodeKind {
+    Dir(BTreeMap<String, INode>),
+    File,
 }
 
-#[derive(Debug, Default)]
-struct FileNodeFile {
-    size: usize
+#[derive(Debug)]
+struct Node {
+    name: String,
+    parent: Option<INode>,
+    kind: NodeKind,
+    // recursive for directories, kept up to date when adding files
+    size: usize,
 }
 
-fn get_dir_from_inode(nodes: &mut Vec<FileNode>, inode: INode) -> &mut FileNodeDir
-{
-    match &mut nodes[inode] {
-        FileNode::Dir(dir) => dir,
-        FileNode::File(_) => panic!("Wanted to get a dir but it is a file")
-    }
+/// Tree of directories and files, the root directory being "/"
+#[derive(Debug)]
+pub struct FileSystem {
+    nodes: Vec<Node>,
 }
 
-fn get_size_recur(nodes: &Vec<FileNode>, inode: INode) -> usize
-{
-    match &nodes[inode] {
-        FileNode::Dir(dir) => dir.children.iter().map(|(_, &inode)| get_size_recur(nodes, inode)).sum(),
-        FileNode::File(file) => file.size
+impl Default for FileSystem {
+    fn default() -> Self
+    {
+        Self::new()
     }
 }
 
-fn build_file_tree<Input>(input: &mut Input) -> Result<Vec<FileNode>>
-where Input: Read
-{
-    let mut nodes: Vec<FileNode> = Vec::new();
-    nodes.push(FileNode::Dir(FileNodeDir::default()));
-    let mut current_dir: INode = 0;
-    let mut outer_dir_stack: Vec<INode> = Vec::new();
-    do_for_each_command(input, |command, ls_output| {
-        match command {
-            Command::Cd(cd_args) => {
-                let dest = cd_args.dest;
-                current_dir = match dest {
-                    CdDest::Inner(dest_name) => {
-                        outer_dir_stack.push(current_dir);
-                        let dest_inode = *get_dir_from_inode(&mut nodes, current_dir).children.get(&dest_name).unwrap();
-                        let dest_node = &nodes[dest_inode];
-                        match dest_node {
-                            FileNode::File(_) => panic!("Cant cd to file {:?}", dest_name),
-                            FileNode::Dir(_) => dest_inode
-                        }
-                    }
-                    CdDest::Outer => outer_dir_stack.pop().unwrap(),
-                    CdDest::Root => 0
-                };
-            },
-            Command::Ls => {
-                let ls_output = ls_output.unwrap();
-                for ls_entry in ls_output {
-                    match ls_entry {
-                        LsEntry::Dir(dir_name) => {
-                            nodes.push(FileNode::Dir(FileNodeDir::default()));
-                            let new_inode = nodes.len() - 1;
-                            get_dir_from_inode(&mut nodes, current_dir).children.insert(dir_name, new_inode);
-                        },
-                        LsEntry::File(file_entry) => {
-                            nodes.push(FileNode::File(FileNodeFile {size: file_entry.size}));
-                            let new_inode = nodes.len() - 1;
-                            get_dir_from_inode(&mut nodes, current_dir).children.insert(file_entry.name, new_inode);
-                        },
-                    };
-                }
-            }
+impl FileSystem {
+    pub fn new() -> Self
+    {
+        Self {
+            nodes: vec![Node {name: String::from("/"), parent: None, kind: NodeKind::Dir(BTreeMap::new()), size: 0}],
         }
-        Ok(())
-    })?;
-    Ok(nodes)
-}
+    }
 
-pub fn day_7_part_1<Input>(input: &mut Input) -> Result<()>
-where Input: Read
-{
-    let nodes = build_file_tree(input)?;
+    /// Replay the `cd` and `ls` commands of a terminal log
+    pub fn from_terminal_log(log: &str) -> Result<Self>
+    {
+        let mut fs = Self::new();
+        let mut current_dir = fs.root();
+        let mut listing = false;
+        do_for_each_line_of_str(log, |line| {
+            let (_, parsed) = parse::parse_line(line).map_err(|_| Error::new_token(0, line.len()))?;
+            // position of the name, for errors
+            let name_token = |name: &str| Error::new_token(line.len() - name.len(), name.len());
+            match parsed {
+                parse::Line::Cd(path) => {
+                    current_dir = fs.resolve(current_dir, &path)
+                        .filter(|&inode| fs.is_dir(inode))
+                        .ok_or_else(|| name_token(&path))?;
+                    listing = false;
+                },
+                parse::Line::Ls => listing = true,
+                _ if !listing => return Err(Error::new_token(0, line.len())),
+                parse::Line::Dir(name) => {
+                    fs.mkdir(current_dir, &name).ok_or_else(|| name_token(&name))?;
+                },
+                parse::Line::File(size, name) => {
+                    fs.add_file(current_dir, &name, size).ok_or_else(|| name_token(&name))?;
+                },
+            }
+            Ok(())
+        })?;
+        Ok(fs)
+    }
 
-    let mut sum = 0;
-    for inode in 0..nodes.len() {
-        if !nodes[inode].is_dir() {
-            continue;
-        }
-        let size = get_size_recur(&nodes, inode);
-        if size <= 100000 {
-            sum += size;
-        }
+    pub fn root(&self) -> INode
+    {
+        0
     }
-    println!("Sum is {}", sum);
 
-    Ok(())
-}
+    pub fn name(&self, inode: INode) -> &str
+    {
+        &self.nodes[inode].name
+    }
 
-fn do_for_each_command<I, F>(input: &mut I, mut func: F) -> Result<()>
-where I: Read,
-F: FnMut(Command, Option<Vec<LsEntry>>) -> Result<()>
-{
-    let content = get_whole_input_as_string(input)?;
-    let mut remaining = content.as_str();
-    while remaining.len() != 0 {
-        let i = remaining;
-        let (i, command) = parse::parse_command(i).unwrap();
-        let (i, _) = parse::get_to_next_line(i).unwrap();
-        let (i, command_output) = match command {
-            Command::Ls => parse::parse_ls_entry_list(i).map(|(i, ls_out)| (i, Some(ls_out))).unwrap(),
-            _ => (i, None)
+    pub fn parent(&self, inode: INode) -> Option<INode>
+    {
+        self.nodes[inode].parent
+    }
+
+    pub fn is_dir(&self, inode: INode) -> bool
+    {
+        matches!(self.nodes[inode].kind, NodeKind::Dir(_))
+    }
+
+    /// Recursive size for directories
+    pub fn size(&self, inode: INode) -> usize
+    {
+        self.nodes[inode].size
+    }
+
+    /// Sorted by name, empty for files
+    pub fn children(&self, inode: INode) -> impl Iterator<Item = INode> + '_
+    {
+        let children = match &self.nodes[inode].kind {
+            NodeKind::Dir(children) => Some(children.values().copied()),
+            NodeKind::File => None,
         };
-        remaining = i;
-        func(command, command_output)?;
+        children.into_iter().flatten()
     }
-    Ok(())
-}
 
-pub fn day_7_part_2<Input>(input: &mut Input) -> Result<()>
-where Input: Read
-{
-    let nodes = build_file_tree(input)?;
+    pub fn dirs(&self) -> impl Iterator<Item = INode> + '_
+    {
+        (0..self.nodes.len()).filter(|&inode| self.is_dir(inode))
+    }
 
-    let mut dir_sizes: Vec<usize> = Vec::new(); // excludes root
-    for inode in 0..nodes.len() {
-        if !nodes[inode].is_dir() {
-            continue;
+    /// Absolute path, directories end with '/'
+    pub fn path(&self, inode: INode) -> String
+    {
+        let mut names = Vec::new();
+        let mut current = inode;
+        while let Some(parent) = self.parent(current) {
+            names.push(self.name(current));
+            current = parent;
+        }
+        let mut ret = String::from("/");
+        for name in names.iter().rev() {
+            ret += name;
+            ret.push('/');
         }
-        dir_sizes.push(get_size_recur(&nodes, inode))
+        if !self.is_dir(inode) {
+            ret.pop();
+        }
+        ret
     }
-    dir_sizes.sort();
 
-    let min_size = 30000000 - (70000000 - get_size_recur(&nodes, 0));
-    let answer = dir_sizes.iter().find(|&&size| size >= min_size).unwrap();
+    /// Follow `path` from `from`, or from the root if it starts with '/', ".." of the root is the root
+    pub fn resolve(&self, from: INode, path: &str) -> Option<INode>
+    {
+        let mut current = if path.starts_with('/') { self.root() } else { from };
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            current = match (name, &self.nodes[current].kind) {
+                (".", _) => current,
+                ("..", _) => self.parent(current).unwrap_or(current),
+                (_, NodeKind::Dir(children)) => *children.get(name)?,
+                (_, NodeKind::File) => return None,
+            };
+        }
+        Some(current)
+    }
 
-    println!("Answer is {}", answer);
+    /// None if `name` is not a valid name or is already a file, the existing directory is returned otherwise
+    pub fn mkdir(&mut self, parent: INode, name: &str) -> Option<INode>
+    {
+        match self.get_or_add(parent, name, NodeKind::Dir(BTreeMap::new())) {
+            Some((inode, _)) if self.is_dir(inode) => Some(inode),
+            _ => None,
+        }
+    }
 
-    Ok(())
-}
+    /// None if `name` is not a valid name or is already a directory or a file of another size
+    pub fn add_file(&mut self, parent: INode, name: &str, size: usize) -> Option<INode>
+    {
+        let (inode, is_new) = self.get_or_add(parent, name, NodeKind::File)?;
+        if is_new {
+            let mut current = Some(inode);
+            while let Some(ancestor) = current {
+                self.nodes[ancestor].size += size;
+                current = self.parent(ancestor);
+            }
+        }
+        let same_file = !self.is_dir(inode) && self.size(inode) == size;
+        same_file.then_some(inode)
+    }
 
-#[derive(Debug)]
-enum Command {
-    Cd(CdArgs),
-    Ls
-}
+    // also returns whether the node was added
+    fn get_or_add(&mut self, parent: INode, name: &str, kind: NodeKind) -> Option<(INode, bool)>
+    {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            return None;
+        }
+        let new_inode = self.nodes.len();
+        let children = match &mut self.nodes[parent].kind {
+            NodeKind::Dir(children) => children,
+            NodeKind::File => return None,
+        };
+        if let Some(&inode) = children.get(name) {
+            return Some((inode, false));
+        }
+        children.insert(name.to_string(), new_inode);
+        self.nodes.push(Node {name: name.to_string(), parent: Some(parent), kind, size: 0});
+        Some((new_inode, true))
+    }
 
-#[derive(Debug)]
-struct CdArgs {
-    dest: CdDest
-}
+    pub fn dirs_with_size_in<R>(&self, range: R) -> impl Iterator<Item = INode> + '_
+    where R: RangeBounds<usize> + 'static
+    {
+        self.dirs().filter(move |&inode| range.contains(&self.size(inode)))
+    }
 
-#[derive(Debug)]
-enum CdDest {
-    Root,
-    Inner(String),
-    Outer
-}
+    /// Smallest directory whose deletion frees at least `needed` bytes
+    pub fn smallest_dir_freeing(&self, needed: usize) -> Option<INode>
+    {
+        self.dirs_with_size_in(needed..).min_by_key(|&inode| self.size(inode))
+    }
 
-#[derive(Debug)]
-enum LsEntry {
-    File(LsEntryFile),
-    Dir(String)
+    /// Drawing of the tree below `inode`, like the `tree` command
+    pub fn tree(&self, inode: INode) -> String
+    {
+        let mut ret = self.describe(inode);
+        ret.push('\n');
+        self.tree_recur(inode, "", &mut ret);
+        ret
+    }
+
+    fn tree_recur(&self, inode: INode, prefix: &str, out: &mut String)
+    {
+        let children: Vec<INode> = self.children(inode).collect();
+        for (i, &child) in children.iter().enumerate() {
+            let is_last = i + 1 == children.len();
+            let (branch, indent) = if is_last { ("└── ", "    ") } else { ("├── ", "│   ") };
+            *out += &format!("{prefix}{branch}{}\n", self.describe(child));
+            self.tree_recur(child, &format!("{prefix}{indent}"), out);
+        }
+    }
+
+    fn describe(&self, inode: INode) -> String
+    {
+        match self.parent(inode) {
+            None => format!("/ ({})", self.size(inode)),
+            Some(_) if self.is_dir(inode) => format!("{}/ ({})", self.name(inode), self.size(inode)),
+            Some(_) => format!("{} {}", self.name(inode), self.size(inode)),
+        }
+    }
 }
 
-#[derive(Debug)]
-struct LsEntryFile {
-    size: usize,
-    name: String
+impl fmt::Display for FileSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.tree(self.root()))
+    }
 }
 
 mod parse {
     use crate::days::parse::*;
-    use super::{Command, CdArgs, CdDest, LsEntry, LsEntryFile};
     use nom::{
         IResult,
-        bytes::complete::{tag, take_till},
-        character::complete::{alpha1, newline, space1},
-        sequence::{tuple, preceded, terminated},
+        bytes::complete::tag,
+        character::complete::space1,
+        sequence::{preceded, separated_pair},
         branch::alt,
-        combinator::map_res,
-        multi::many0
+        combinator::{all_consuming, map, rest, verify}
     };
 
-    fn parse_cd_cmd(i: &str) -> IResult<&str, Command>
-    {
-        let (i, dest) = preceded(tag("cd "), alt((
-            map_res(tag("/"), |_| Ok::<CdDest, ()>(CdDest::Root)),
-            map_res(tag(".."), |_| Ok::<CdDest, ()>(CdDest::Outer)),
-            map_res(alpha1, |s: &str| Ok::<CdDest, ()>(CdDest::Inner(s.to_string())))
-        )))(i)?;
-
-        Ok((i,
-            Command::Cd(CdArgs {dest})
-        ))
+    pub(super) enum Line {
+        Cd(String),
+        Ls,
+        Dir(String),
+        File(usize, String),
     }
 
-    fn parse_ls_cmd(i: &str) -> IResult<&str, Command>
+    // anything up to the end of the line
+    fn parse_name(i: &str) -> IResult<&str, String>
     {
-        map_res(tag("ls"), |_| Ok::<Command, ()>(Command::Ls))(i)
+        map(verify(rest, |s: &str| !s.is_empty()), String::from)(i)
     }
 
-    pub(super) fn parse_command(i: &str) -> IResult<&str, Command>
+    pub(super) fn parse_line(i: &str) -> IResult<&str, Line>
     {
-        preceded(tag("$ "), alt((parse_cd_cmd, parse_ls_cmd)))(i)
+        all_consuming(alt((
+            map(preceded(tag("$ cd "), parse_name), Line::Cd),
+            map(tag("$ ls"), |_| Line::Ls),
+            map(preceded(tag("dir "), parse_name), Line::Dir),
+            map(separated_pair(parse_int::<usize, _>, space1, parse_name), |(size, name)| Line::File(size, name)),
+        )))(i)
     }
+}
 
-    fn parse_name(i: &str) -> IResult<&str, String>
-    {
-        map_res(take_till(|c| c == '\n'), |s: &str| Ok::<String, ()>(s.to_string()))(i)
-    }
+#[cfg(test)]
+mod test {
+    use super::*;
 
-    pub(super) fn parse_ls_entry(i: &str) -> IResult<&str, LsEntry>
-    {
-        let parse_ls_entry_file = 
-            map_res(
-                tuple((parse_int::<usize, _>, preceded(space1, parse_name))),
-                |(size, name)| Ok::<LsEntry, ()>(LsEntry::File(LsEntryFile { size, name }))
-                );
-        alt((
-            map_res(preceded(tag("dir "), parse_name), |name| Ok::<LsEntry, ()>(LsEntry::Dir(name))),
-            parse_ls_entry_file
-        ))(i)
-    }
+    const EXAMPLE: &str = "\
+$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k
+";
 
-    pub(super) fn get_to_next_line(i: &str) -> IResult<&str, &str>
-    {
-        terminated(take_till(|c| c == '\n'), newline)(i)
+    #[test]
+    fn test_example() {
+        let fs = FileSystem::from_terminal_log(EXAMPLE).unwrap();
+        assert_eq!(fs.size(fs.root()), 48381165);
+        assert_eq!(fs.dirs_with_size_in(..=100000).map(|inode| fs.size(inode)).sum::<usize>(), 95437);
+        let freed = fs.smallest_dir_freeing(30000000 - (70000000 - 48381165)).unwrap();
+        assert_eq!(fs.path(freed), "/d/");
+        assert_eq!(fs.size(freed), 24933642);
+
+        let e = fs.resolve(fs.root(), "/a/e").unwrap();
+        assert_eq!(fs.resolve(e, "../../d/./j").map(|inode| fs.path(inode)), Some(String::from("/d/j")));
+        assert_eq!(fs.resolve(e, "i/x"), None);
+
+        assert!(fs.tree(fs.root()).starts_with("\
+/ (48381165)
+├── a/ (94853)
+│   ├── e/ (584)
+│   │   └── i 584
+│   ├── f 29116
+"));
     }
 
-    pub(super) fn parse_ls_entry_list(i: &str) -> IResult<&str, Vec<LsEntry>>
-    {
-        many0(terminated(parse_ls_entry, get_to_next_line))(i)
+    #[test]
+    fn test_names_and_errors() {
+        let fs = FileSystem::from_terminal_log("$ cd /\n$ ls\ndir a.b\n$ cd a.b\n$ ls\ndir x-1\n$ cd x-1\n$ ls\n12 my file\n$ ls\n12 my file\n").unwrap();
+        assert_eq!(fs.size(fs.root()), 12);
+        assert_eq!(fs.path(fs.resolve(0, "a.b/x-1/my file").unwrap()), "/a.b/x-1/my file");
+
+        let err = FileSystem::from_terminal_log("$ cd /\n$ cd missing\n").unwrap_err();
+        assert!(matches!(err, Error::Parsing(parsing) if parsing.line == 2 && parsing.token.as_ref().is_some_and(|token| token.line_pos == 5)));
+        assert!(FileSystem::from_terminal_log("$ ls\n12 f\n13 f\n").is_err());
+        assert!(FileSystem::from_terminal_log("$ rm -rf /\n").is_err());
+        assert!(day_7_part_2(&mut "$ cd /\n$ ls\n70000001 big\n".as_bytes()).is_err());
     }
-}
\ No newline at end of file
+}
@@ -1,102 +1,140 @@
 use crate::days::internal_common::*;
+use crate::days::directions::Direction4 as Direction;
+use crate::days::points::BoundingBox;
+use crate::days::simulation::{self, RunOptions, Simulation, StepOutcome};
 use std::collections::HashSet;
 
-type Position = (i32, i32);
+type Position = crate::days::points::Point2<i32>;
 
-fn move_in_direction(pos: &mut Position, dir: Direction)
+fn is_pulling(head_pos: Position, tail_pos: Position) -> bool
 {
-    match dir {
-        Direction::U => pos.1 += 1,
-        Direction::D => pos.1 -= 1,
-        Direction::L => pos.0 -= 1,
-        Direction::R => pos.0 += 1,
-    }
+    head_pos.chebyshev_distance(&tail_pos) > 1
 }
 
-fn is_pulling(head_pos: Position, tail_pos: Position) -> bool
+fn pull(tail: &mut Position, head: Position)
 {
-    (head_pos.0 - tail_pos.0).abs() > 1
-    || (head_pos.1 - tail_pos.1).abs() > 1
+    *tail += (head - *tail).signum();
 }
 
-fn pull_for_part_2(tail: &mut Position, head: Position)
+pub fn day_9_part_1<Input>(input: &mut Input) -> Result<()>
+where Input: Read
 {
-    if head.0 - tail.0 < 0 {
-        tail.0 -= 1;
-    }
-    else if head.0 - tail.0 > 0 {
-        tail.0 += 1;
-    }
+    solve(input, 2, &RunOptions::default())
+}
 
-    if head.1 - tail.1< 0 {
-        tail.1 -= 1;
-    }
-    else if head.1 - tail.1 > 0 {
-        tail.1 += 1;
-    }
+pub fn day_9_part_1_simulate<Input>(input: &mut Input, options: &RunOptions) -> Result<()>
+where Input: Read
+{
+    solve(input, 2, options)
 }
 
-pub fn day_9_part_1<Input>(input: &mut Input) -> Result<()>
+pub fn day_9_part_2<Input>(input: &mut Input) -> Result<()>
 where Input: Read
 {
-    let content = get_whole_input_as_string(input)?;
+    solve(input, 10, &RunOptions::default())
+}
 
-    let mut visited_pos: HashSet<Position> = HashSet::new();
-    let mut head_pos: Position = (0, 0);
-    let mut tail_pos: Position = (0, 0);
-    visited_pos.insert(tail_pos);
+pub fn day_9_part_2_simulate<Input>(input: &mut Input, options: &RunOptions) -> Result<()>
+where Input: Read
+{
+    solve(input, 10, options)
+}
+
+fn solve<Input>(input: &mut Input, num_knots: usize, options: &RunOptions) -> Result<()>
+where Input: Read
+{
+    let content = get_whole_input_as_string(input)?;
 
+    let mut moves = Vec::new();
     parse::parse_and_do_for_each_line(content.as_str(), |dir, num_steps| {
-        for _ in 0..num_steps {
-            let previous_head_pos = head_pos;
-            move_in_direction(&mut head_pos, dir);
-            if is_pulling(head_pos, tail_pos) {
-                tail_pos = previous_head_pos;
-                visited_pos.insert(tail_pos);
-            }
-        }
+        moves.extend(std::iter::repeat_n(dir, num_steps));
         Ok(())
     })?;
 
-    println!("Num of visited positions is {}", visited_pos.len());
+    let mut rope = Rope::new(num_knots, moves);
+    simulation::run(&mut rope, options, &mut std::io::stdout().lock())?;
+
+    println!("Num of visited positions is {}", rope.visited_pos.len());
 
     Ok(())
 }
 
-pub fn day_9_part_2<Input>(input: &mut Input) -> Result<()>
-where Input: Read
-{
-    let content = get_whole_input_as_string(input)?;
+// the head moves by one position at each step
+struct Rope {
+    knots: Vec<Position>,
+    visited_pos: HashSet<Position>,
+    moves: Vec<Direction>,
+    current_move_id: usize,
+}
 
-    let mut visited_pos: HashSet<Position> = HashSet::new();
-    let mut rope_pos: Vec<Position> = vec![(0, 0); 10];
-    visited_pos.insert(rope_pos[9]);
+impl Rope {
+    fn new(num_knots: usize, moves: Vec<Direction>) -> Self
+    {
+        let knots = vec![Position::from_xy(0, 0); num_knots];
+        Self {
+            visited_pos: HashSet::from([knots[num_knots - 1]]),
+            knots,
+            moves,
+            current_move_id: 0,
+        }
+    }
 
-    parse::parse_and_do_for_each_line(content.as_str(), |dir, num_steps| {
-        for _ in 0..num_steps {
-            move_in_direction(&mut rope_pos[0], dir);
-            for i in 1..10 {
-                if is_pulling(rope_pos[i], rope_pos[i - 1]) {
-                    let head = rope_pos[i - 1];
-                    pull_for_part_2(&mut rope_pos[i], head);
-                }
+    fn get_knot_char(&self, knot_id: usize) -> char
+    {
+        match knot_id {
+            0 => 'H',
+            _ if knot_id == self.knots.len() - 1 => 'T',
+            _ => char::from_digit(knot_id as u32, 36).unwrap_or('*'),
+        }
+    }
+}
+
+impl Simulation for Rope {
+    fn step(&mut self) -> StepOutcome
+    {
+        let dir = self.moves[self.current_move_id];
+        self.current_move_id += 1;
+
+        self.knots[0] += dir.to_point();
+        for i in 1..self.knots.len() {
+            if is_pulling(self.knots[i], self.knots[i - 1]) {
+                let head = self.knots[i - 1];
+                pull(&mut self.knots[i], head);
             }
-            visited_pos.insert(rope_pos[9]);
         }
-        Ok(())
-    })?;
+        self.visited_pos.insert(*self.knots.last().unwrap());
 
-    println!("Num of visited positions is {}", visited_pos.len());
+        if self.is_done() { StepOutcome::Done } else { StepOutcome::Continue }
+    }
 
-    Ok(())
-}
+    // visited positions with '#', start with 's'
+    fn render(&self) -> String
+    {
+        let start = Position::from_xy(0, 0);
+        let mut bbox = BoundingBox::from_point(&start);
+        self.visited_pos.iter().chain(&self.knots).for_each(|p| bbox.update(p));
+
+        let mut ret = String::new();
+        for y in bbox.min.y..=bbox.max.y {
+            for x in bbox.min.x..=bbox.max.x {
+                let p = Position::from_xy(x, y);
+                let c = match self.knots.iter().position(|knot| *knot == p) {
+                    Some(knot_id) => self.get_knot_char(knot_id),
+                    None if p == start => 's',
+                    None if self.visited_pos.contains(&p) => '#',
+                    None => '.',
+                };
+                ret.push(c);
+            }
+            ret.push('\n');
+        }
+        ret
+    }
 
-#[derive(Clone, Copy, Debug)]
-enum Direction {
-    U,
-    D,
-    R,
-    L
+    fn is_done(&self) -> bool
+    {
+        self.current_move_id == self.moves.len()
+    }
 }
 
 mod parse {
@@ -112,16 +150,10 @@ mod parse {
     where F: FnMut(super::Direction, usize) -> super::Result<()>
     {
         let mut i = input;
-        while i.len() != 0 {
+        while !i.is_empty() {
 
             let parse_dir = context("parse_dir", map_res(anychar,
-                |c| match c {
-                    'U' => Ok(super::Direction::U),
-                    'D' => Ok(super::Direction::D),
-                    'L' => Ok(super::Direction::L),
-                    'R' => Ok(super::Direction::R),
-                    _ => Err(())
-                }
+                |c| super::Direction::from_char(c).ok_or(())
             ));
 
             let res = terminated(
@@ -138,4 +170,23 @@ mod parse {
         Ok(())
     }
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let moves = [Direction::Right; 4].into_iter().chain([Direction::Up; 4]).collect();
+        let mut rope = Rope::new(2, moves);
+        assert_eq!(simulation::run_to_end(&mut rope), 8);
+        assert_eq!(rope.render(), "\
+....H
+....T
+....#
+....#
+s###.
+");
+    }
+}
@@ -0,0 +1,232 @@
+use crate::days::points::{Point2, Signed};
+
+// All directions assume grid coordinates, where x points right and y points down.
+
+/// One of the 4 axis-aligned directions.
+/// Declared in the order of the AoC facing numbers (right is 0, then clockwise).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Direction4 {
+    Right,
+    Down,
+    Left,
+    Up,
+}
+
+/// One of the 8 compass headings, clockwise from north (up)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Direction8 {
+    N,
+    NE,
+    E,
+    SE,
+    S,
+    SW,
+    W,
+    NW,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Turn {
+    Left,
+    Right,
+}
+
+impl Direction4 {
+    pub const ALL: [Self; 4] = [Self::Right, Self::Down, Self::Left, Self::Up];
+
+    pub fn turn(&self, turn: Turn) -> Self
+    {
+        match turn {
+            Turn::Left => self.turn_left(),
+            Turn::Right => self.turn_right(),
+        }
+    }
+
+    pub fn turn_left(&self) -> Self
+    {
+        Self::from_facing(self.facing() + 3)
+    }
+
+    pub fn turn_right(&self) -> Self
+    {
+        Self::from_facing(self.facing() + 1)
+    }
+
+    pub fn reverse(&self) -> Self
+    {
+        Self::from_facing(self.facing() + 2)
+    }
+
+    /// Facing number as scored by AoC: 0 for right, 1 for down, 2 for left, 3 for up
+    pub fn facing(&self) -> u8
+    {
+        *self as u8
+    }
+
+    /// Inverse of `facing`, wrapping around every 4
+    pub fn from_facing(facing: u8) -> Self
+    {
+        Self::ALL[(facing % 4) as usize]
+    }
+
+    pub fn to_point<T>(&self) -> Point2<T>
+    where T: Signed
+    {
+        let (zero, one) = (T::zero(), T::one());
+        match self {
+            Self::Right => Point2::from_xy(one, zero),
+            Self::Down => Point2::from_xy(zero, one),
+            Self::Left => Point2::from_xy(-one, zero),
+            Self::Up => Point2::from_xy(zero, -one),
+        }
+    }
+
+    /// None if the point is not a unit vector along an axis
+    pub fn from_point<T>(point: &Point2<T>) -> Option<Self>
+    where T: Signed
+    {
+        Self::ALL.into_iter().find(|dir| dir.to_point::<T>() == *point)
+    }
+
+    /// Accepts `U/D/L/R`, `^v<>` and `N/S/E/W`
+    pub fn from_char(c: char) -> Option<Self>
+    {
+        match c {
+            'R' | '>' | 'E' => Some(Self::Right),
+            'D' | 'v' | 'S' => Some(Self::Down),
+            'L' | '<' | 'W' => Some(Self::Left),
+            'U' | '^' | 'N' => Some(Self::Up),
+            _ => None
+        }
+    }
+
+    /// Arrow character, as drawn in the puzzle descriptions
+    pub fn to_arrow(&self) -> char
+    {
+        match self {
+            Self::Right => '>',
+            Self::Down => 'v',
+            Self::Left => '<',
+            Self::Up => '^',
+        }
+    }
+}
+
+impl Direction8 {
+    pub const ALL: [Self; 8] = [Self::N, Self::NE, Self::E, Self::SE, Self::S, Self::SW, Self::W, Self::NW];
+
+    /// Turn by 45°
+    pub fn turn_left(&self) -> Self
+    {
+        Self::ALL[(*self as usize + 7) % 8]
+    }
+
+    /// Turn by 45°
+    pub fn turn_right(&self) -> Self
+    {
+        Self::ALL[(*self as usize + 1) % 8]
+    }
+
+    pub fn reverse(&self) -> Self
+    {
+        Self::ALL[(*self as usize + 4) % 8]
+    }
+
+    pub fn to_point<T>(&self) -> Point2<T>
+    where T: Signed
+    {
+        let (zero, one) = (T::zero(), T::one());
+        match self {
+            Self::N => Point2::from_xy(zero, -one),
+            Self::NE => Point2::from_xy(one, -one),
+            Self::E => Point2::from_xy(one, zero),
+            Self::SE => Point2::from_xy(one, one),
+            Self::S => Point2::from_xy(zero, one),
+            Self::SW => Point2::from_xy(-one, one),
+            Self::W => Point2::from_xy(-one, zero),
+            Self::NW => Point2::from_xy(-one, -one),
+        }
+    }
+
+    /// None if the point is not one of the 8 unit offsets
+    pub fn from_point<T>(point: &Point2<T>) -> Option<Self>
+    where T: Signed
+    {
+        Self::ALL.into_iter().find(|dir| dir.to_point::<T>() == *point)
+    }
+
+    /// Accepts the same characters as `Direction4::from_char`
+    pub fn from_char(c: char) -> Option<Self>
+    {
+        Direction4::from_char(c).map(Self::from)
+    }
+}
+
+impl From<Direction4> for Direction8 {
+    fn from(dir: Direction4) -> Self
+    {
+        match dir {
+            Direction4::Right => Self::E,
+            Direction4::Down => Self::S,
+            Direction4::Left => Self::W,
+            Direction4::Up => Self::N,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_turns() {
+        for dir in Direction4::ALL {
+            assert_eq!(dir.turn_left().turn_right(), dir);
+            assert_eq!(dir.turn_right().turn_right(), dir.reverse());
+            assert_eq!(dir.reverse().reverse(), dir);
+            assert_eq!(dir.turn(Turn::Right).to_point::<i32>(), dir.to_point::<i32>().rotate(1));
+            assert_eq!(dir.turn(Turn::Left).to_point::<i32>(), dir.to_point::<i32>().rotate(-1));
+        }
+        assert_eq!(Direction4::Right.turn_right(), Direction4::Down);
+        assert_eq!(Direction4::Up.turn_left(), Direction4::Left);
+
+        for dir in Direction8::ALL {
+            assert_eq!(dir.turn_left().turn_right(), dir);
+            assert_eq!(dir.turn_right().turn_right().turn_right().turn_right(), dir.reverse());
+            assert_eq!(dir.reverse().to_point::<i32>(), -dir.to_point::<i32>());
+        }
+        assert_eq!(Direction8::N.turn_left(), Direction8::NW);
+    }
+
+    #[test]
+    fn test_points() {
+        for dir in Direction4::ALL {
+            assert_eq!(Direction4::from_point(&dir.to_point::<i64>()), Some(dir));
+        }
+        for dir in Direction8::ALL {
+            assert_eq!(Direction8::from_point(&dir.to_point::<i8>()), Some(dir));
+        }
+        assert_eq!(Direction4::Up.to_point::<i32>(), Point2::from_xy(0, -1));
+        assert_eq!(Direction4::from_point(&Point2::from_xy(1, 1)), None);
+        assert_eq!(Direction8::from_point(&Point2::from_xy(1, 1)), Some(Direction8::SE));
+        assert_eq!(Direction8::from_point(&Point2::from_xy(0, 0)), None);
+    }
+
+    #[test]
+    fn test_parse_and_facing() {
+        for (chars, dir) in ["UN^", "DSv", "LW<", "RE>"].iter().zip([Direction4::Up, Direction4::Down, Direction4::Left, Direction4::Right]) {
+            assert!(chars.chars().all(|c| Direction4::from_char(c) == Some(dir)));
+            assert_eq!(Direction4::from_char(dir.to_arrow()), Some(dir));
+        }
+        assert_eq!(Direction4::from_char('x'), None);
+        assert_eq!(Direction8::from_char('W'), Some(Direction8::W));
+
+        assert_eq!(Direction4::Right.facing(), 0);
+        assert_eq!(Direction4::Down.facing(), 1);
+        assert_eq!(Direction4::Left.facing(), 2);
+        assert_eq!(Direction4::Up.facing(), 3);
+        for dir in Direction4::ALL {
+            assert_eq!(Direction4::from_facing(dir.facing()), dir);
+        }
+    }
+}
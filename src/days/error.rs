@@ -11,7 +11,13 @@ pub enum Error {
     #[error("No solution")]
     NoSolution,
     #[error("Parsing error")]
-    ParsingWithVerboseErrorMessage(String)
+    ParsingWithVerboseErrorMessage(String),
+    #[error("I/O error")]
+    Io(#[from] std::io::Error),
+    #[error("Unknown glyphs at columns {0:?}")]
+    UnknownGlyphs(Vec<usize>),
+    #[error("Unsupported: {0}")]
+    Unsupported(String)
 }
 
 #[derive(Debug)]
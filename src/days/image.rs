@@ -0,0 +1,155 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+pub type Rgb = [u8; 3];
+
+pub const BLACK: Rgb = [0, 0, 0];
+pub const WHITE: Rgb = [255, 255, 255];
+
+/// RGB raster, exported as binary PPM (P6) or PGM (P5)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    /// Row-major, from the top left corner
+    pub pixels: Vec<Rgb>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Self
+    {
+        Self {
+            width,
+            height,
+            pixels: vec![BLACK; width * height],
+        }
+    }
+
+    pub fn from_fn<F>(width: usize, height: usize, mut pixel: F) -> Self
+    where F: FnMut(usize, usize) -> Rgb
+    {
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push(pixel(x, y));
+            }
+        }
+        Self { width, height, pixels }
+    }
+
+    /// One pixel per cell of a row-major grid
+    pub fn from_grid<T, F>(cells: &[T], width: usize, mut color: F) -> Self
+    where F: FnMut(&T) -> Rgb
+    {
+        let height = cells.len().checked_div(width).unwrap_or(0);
+        Self {
+            width,
+            height,
+            pixels: cells[..(width * height)].iter().map(&mut color).collect(),
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Rgb
+    {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, rgb: Rgb)
+    {
+        self.pixels[y * self.width + x] = rgb;
+    }
+
+    /// Every pixel becomes a `factor` x `factor` square
+    pub fn scaled(&self, factor: usize) -> Self
+    {
+        Self::from_fn(self.width * factor, self.height * factor, |x, y| self.get(x / factor, y / factor))
+    }
+
+    /// Upside down, for grids with y going up
+    pub fn flipped_vertically(&self) -> Self
+    {
+        Self::from_fn(self.width, self.height, |x, y| self.get(x, self.height - 1 - y))
+    }
+
+    pub fn write_ppm<W: Write>(&self, writer: &mut W) -> io::Result<()>
+    {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        for pixel in &self.pixels {
+            writer.write_all(pixel)?;
+        }
+        Ok(())
+    }
+
+    /// Grayscale, using the luma of every pixel
+    pub fn write_pgm<W: Write>(&self, writer: &mut W) -> io::Result<()>
+    {
+        write!(writer, "P5\n{} {}\n255\n", self.width, self.height)?;
+        for &[r, g, b] in &self.pixels {
+            let luma = (299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000;
+            writer.write_all(&[luma as u8])?;
+        }
+        Ok(())
+    }
+
+    /// PGM if the file extension is "pgm", PPM otherwise
+    pub fn save(&self, path: &Path) -> io::Result<()>
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("pgm") => self.write_pgm(&mut writer)?,
+            _ => self.write_ppm(&mut writer)?,
+        }
+        writer.flush()
+    }
+}
+
+/// Where and how big the days supporting it should draw their final state
+#[derive(Debug, Clone)]
+pub struct RenderTarget {
+    pub path: PathBuf,
+    pub scale: usize,
+}
+
+impl RenderTarget {
+    pub fn save(&self, image: &Image) -> io::Result<()>
+    {
+        image.scaled(self.scale).save(&self.path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_grid_and_scale() {
+        let cells = [0u8, 1, 1, 0, 2, 2];
+        let palette = |c: &u8| [*c * 100, 0, 0];
+        let image = Image::from_grid(&cells, 3, palette);
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(image.get(1, 0), [100, 0, 0]);
+        assert_eq!(image.get(0, 1), BLACK);
+
+        let scaled = image.scaled(2);
+        assert_eq!((scaled.width, scaled.height), (6, 4));
+        assert_eq!(scaled.get(3, 1), [100, 0, 0]);
+        assert_eq!(scaled.get(5, 3), [200, 0, 0]);
+
+        assert_eq!(image.flipped_vertically().get(1, 1), [100, 0, 0]);
+    }
+
+    #[test]
+    fn test_formats() {
+        let mut image = Image::new(2, 1);
+        image.set(1, 0, WHITE);
+
+        let mut ppm = Vec::new();
+        image.write_ppm(&mut ppm).unwrap();
+        assert_eq!(ppm, b"P6\n2 1\n255\n\x00\x00\x00\xff\xff\xff");
+
+        let mut pgm = Vec::new();
+        image.write_pgm(&mut pgm).unwrap();
+        assert_eq!(pgm, b"P5\n2 1\n255\n\x00\xff");
+    }
+}
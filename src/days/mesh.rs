@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use crate::days::points::Point3;
+
+pub type Vertex = Point3<i32>;
+
+/// Named triangle mesh, exported as Wavefront OBJ or ASCII STL
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    pub name: String,
+    pub vertices: Vec<Vertex>,
+    /// Indices in `vertices`, counterclockwise seen from the outside
+    pub triangles: Vec<[usize; 3]>,
+    vertex_ids: HashMap<Vertex, usize>,
+}
+
+impl Mesh {
+    pub fn new(name: &str) -> Self
+    {
+        Self {
+            name: String::from(name),
+            ..Self::default()
+        }
+    }
+
+    /// Square between the unit cube at `cell` and the one at `cell + normal`, `normal` being a unit vector along an axis.
+    /// Faces share their vertices, so the faces around a set of cubes make a watertight mesh.
+    pub fn add_voxel_face(&mut self, cell: Vertex, normal: Vertex)
+    {
+        let axis = (0..3).find(|&axis| normal[axis] != 0).expect("Null normal");
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        let mut corners = [(0, 0), (1, 0), (1, 1), (0, 1)].map(|(du, dv)| {
+            let mut corner = cell;
+            if normal[axis] > 0 {
+                corner[axis] += 1;
+            }
+            corner[u] += du;
+            corner[v] += dv;
+            corner
+        });
+        if normal[axis] < 0 {
+            corners.reverse();
+        }
+        let [a, b, c, d] = corners.map(|corner| self.vertex_id(corner));
+        self.triangles.push([a, b, c]);
+        self.triangles.push([a, c, d]);
+    }
+
+    fn vertex_id(&mut self, vertex: Vertex) -> usize
+    {
+        let vertices = &mut self.vertices;
+        *self.vertex_ids.entry(vertex).or_insert_with(|| {
+            vertices.push(vertex);
+            vertices.len() - 1
+        })
+    }
+
+    // not normalized
+    fn normal(&self, triangle: &[usize; 3]) -> Vertex
+    {
+        let [a, b, c] = triangle.map(|id| self.vertices[id]);
+        (b - a).cross(&(c - a))
+    }
+}
+
+/// One object per mesh
+pub fn write_obj<W: Write>(meshes: &[Mesh], writer: &mut W) -> io::Result<()>
+{
+    // indices are global and start at 1
+    let mut first_id = 1;
+    for mesh in meshes {
+        writeln!(writer, "o {}", mesh.name)?;
+        for vertex in &mesh.vertices {
+            writeln!(writer, "v {} {} {}", vertex.x, vertex.y, vertex.z)?;
+        }
+        for [a, b, c] in &mesh.triangles {
+            writeln!(writer, "f {} {} {}", first_id + a, first_id + b, first_id + c)?;
+        }
+        first_id += mesh.vertices.len();
+    }
+    Ok(())
+}
+
+/// One solid per mesh
+pub fn write_stl<W: Write>(meshes: &[Mesh], writer: &mut W) -> io::Result<()>
+{
+    for mesh in meshes {
+        writeln!(writer, "solid {}", mesh.name)?;
+        for triangle in &mesh.triangles {
+            let normal = mesh.normal(triangle);
+            writeln!(writer, "  facet normal {} {} {}", normal.x, normal.y, normal.z)?;
+            writeln!(writer, "    outer loop")?;
+            for vertex in triangle.map(|id| mesh.vertices[id]) {
+                writeln!(writer, "      vertex {} {} {}", vertex.x, vertex.y, vertex.z)?;
+            }
+            writeln!(writer, "    endloop")?;
+            writeln!(writer, "  endfacet")?;
+        }
+        writeln!(writer, "endsolid {}", mesh.name)?;
+    }
+    Ok(())
+}
+
+/// STL if the file extension is "stl", OBJ otherwise
+pub fn save(meshes: &[Mesh], path: &Path) -> io::Result<()>
+{
+    let mut writer = BufWriter::new(File::create(path)?);
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("stl") => write_stl(meshes, &mut writer)?,
+        _ => write_obj(meshes, &mut writer)?,
+    }
+    writer.flush()
+}
+
+/// Where the days supporting it should export their shapes
+#[derive(Debug, Clone)]
+pub struct MeshTarget {
+    pub path: PathBuf,
+    /// Leave out the faces which cannot be seen from the outside
+    pub exterior_only: bool,
+}
+
+impl MeshTarget {
+    pub fn save(&self, meshes: &[Mesh]) -> io::Result<()>
+    {
+        save(meshes, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // every edge goes both ways as many times
+    fn is_watertight(mesh: &Mesh) -> bool {
+        let mut edges: HashMap<(usize, usize), i32> = HashMap::new();
+        for &[a, b, c] in &mesh.triangles {
+            for (from, to) in [(a, b), (b, c), (c, a)] {
+                *edges.entry((from, to)).or_default() += 1;
+                *edges.entry((to, from)).or_default() -= 1;
+            }
+        }
+        edges.values().all(|&count| count == 0)
+    }
+
+    #[test]
+    fn test_cubes() {
+        let cells = [Vertex::from_xyz(0, 0, 0), Vertex::from_xyz(1, 0, 0)];
+        let mut mesh = Mesh::new("two_cubes");
+        for cell in cells {
+            for neighbor in cell.axis_neighbors().filter(|n| !cells.contains(n)) {
+                mesh.add_voxel_face(cell, neighbor - cell);
+            }
+        }
+        assert_eq!((mesh.vertices.len(), mesh.triangles.len()), (12, 20));
+        assert!(is_watertight(&mesh));
+        // normals point away from the cubes
+        assert!(mesh.triangles.iter().all(|triangle| {
+            let normal = mesh.normal(triangle);
+            let center = triangle.map(|id| mesh.vertices[id]).iter().fold(Vertex::from_xyz(0, 0, 0), |sum, v| sum + *v);
+            // six times the centroid of the triangle and of the cubes
+            (center + center - Vertex::from_xyz(6, 3, 3)).dot(&normal) > 0
+        }));
+
+        let mut stl = Vec::new();
+        write_stl(&[mesh.clone()], &mut stl).unwrap();
+        let stl = String::from_utf8(stl).unwrap();
+        assert!(stl.starts_with("solid two_cubes\n  facet normal "));
+        assert_eq!(stl.matches("vertex ").count(), 60);
+
+        let mut obj = Vec::new();
+        write_obj(&[mesh.clone(), mesh], &mut obj).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+        assert_eq!(obj.lines().filter(|line| line.starts_with("f ")).count(), 40);
+        assert!(obj.lines().any(|line| line.starts_with("f ") && line.split(' ').any(|id| id == "24")));
+        assert!(!obj.lines().any(|line| line.starts_with("f ") && line.split(' ').any(|id| id == "0" || id == "25")));
+    }
+}
@@ -27,7 +27,19 @@ pub mod day_25;
 pub mod y2020_day_1;
 pub mod y2020_day_25;
 mod parse;
-mod points;
+pub mod points;
+pub mod directions;
+pub mod search;
+pub mod regions;
+pub mod cycles;
+pub mod ranges;
+pub mod annealing;
+pub mod branch_and_bound;
+pub mod top_k;
+pub mod image;
+pub mod mesh;
+pub mod simulation;
+pub mod ocr;
 
 pub use error::{Error, Result, Parsing};
 
@@ -53,8 +65,79 @@ macro_rules! make_days_funcs_names_and_ptrs {
 }
 make_days_funcs_names_and_ptrs!(25, std::fs::File);
 
+/// How much the days print besides their answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet,
+    /// Intermediate results, such as drawings
+    Verbose,
+    /// Every step of the computation
+    Trace,
+}
+
+static VERBOSITY: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(Verbosity::Quiet as u8);
+
+pub fn set_verbosity(verbosity: Verbosity)
+{
+    VERBOSITY.store(verbosity as u8, std::sync::atomic::Ordering::Relaxed);
+}
+
+pub fn is_verbose() -> bool
+{
+    VERBOSITY.load(std::sync::atomic::Ordering::Relaxed) >= Verbosity::Verbose as u8
+}
+
+pub fn is_tracing() -> bool
+{
+    VERBOSITY.load(std::sync::atomic::Ordering::Relaxed) >= Verbosity::Trace as u8
+}
+
+pub type RenderFn = fn(&mut std::fs::File, &image::RenderTarget) -> Result<()>;
+
+// days which can draw their final state, see image::RenderTarget
+pub const DAYS_RENDER_FUNCS: [(&str, RenderFn); 7] = [
+    ("day_14_part_1", day_14::day_14_part_1_render),
+    ("day_14_part_2", day_14::day_14_part_2_render),
+    ("day_17_part_1", day_17::day_17_part_1_render),
+    ("day_23_part_1", day_23::day_23_part_1_render),
+    ("day_23_part_2", day_23::day_23_part_2_render),
+    ("day_24_part_1", day_24::day_24_part_1_render),
+    ("day_24_part_2", day_24::day_24_part_2_render),
+];
+
+pub type MeshFn = fn(&mut std::fs::File, &mesh::MeshTarget) -> Result<()>;
+
+// days which can export their shapes as a 3D mesh, see mesh::MeshTarget
+pub const DAYS_MESH_FUNCS: [(&str, MeshFn); 2] = [
+    ("day_18_part_1", day_18::day_18_mesh),
+    ("day_18_part_2", day_18::day_18_mesh),
+];
+
+pub type DotFn = fn(&mut std::fs::File, &std::path::Path) -> Result<()>;
+
+// days which can export their graphs in Graphviz DOT
+pub const DAYS_DOT_FUNCS: [(&str, DotFn); 2] = [
+    ("day_16_part_1", day_16::day_16_part_1_dot),
+    ("day_16_part_2", day_16::day_16_part_2_dot),
+];
+
+pub type SimulateFn = fn(&mut std::fs::File, &simulation::RunOptions) -> Result<()>;
+
+// days which can be run step by step, see simulation::Simulation
+pub const DAYS_SIMULATE_FUNCS: [(&str, SimulateFn); 9] = [
+    ("day_9_part_1", day_9::day_9_part_1_simulate),
+    ("day_9_part_2", day_9::day_9_part_2_simulate),
+    ("day_14_part_1", day_14::day_14_part_1_simulate),
+    ("day_14_part_2", day_14::day_14_part_2_simulate),
+    ("day_17_part_1", day_17::day_17_part_1_simulate),
+    ("day_23_part_1", day_23::day_23_part_1_simulate),
+    ("day_23_part_2", day_23::day_23_part_2_simulate),
+    ("day_24_part_1", day_24::day_24_part_1_simulate),
+    ("day_24_part_2", day_24::day_24_part_2_simulate),
+];
+
 mod internal_common {
-    pub use super::{Result, Error};
+    pub use super::{Result, Error, is_verbose, is_tracing};
     pub use std::io::Read;
 
     pub trait Grid2DTypes {
@@ -68,6 +151,16 @@ mod internal_common {
         fn get_node_right(&self, current: &Self::Node) -> Option<Self::Node>;
         fn get_node_up(&self, current: &Self::Node) -> Option<Self::Node>;
         fn get_node_down(&self, current: &Self::Node) -> Option<Self::Node>;
+
+        fn get_neighbors(&self, current: &Self::Node) -> Vec<Self::Node>
+        {
+            [
+                self.get_node_left(current),
+                self.get_node_right(current),
+                self.get_node_up(current),
+                self.get_node_down(current),
+            ].into_iter().flatten().collect()
+        }
     }
 
     pub trait WithDataType {
@@ -114,11 +207,18 @@ mod internal_common {
         Ok(content)
     }
 
-    pub fn do_for_each_line<I, F>(input: &mut I, mut func: F) -> Result<()>
+    pub fn do_for_each_line<I, F>(input: &mut I, func: F) -> Result<()>
     where I: Read,
     F: FnMut(&str) -> Result<()>
     {
         let content = get_whole_input_as_string(input)?;
+        do_for_each_line_of_str(&content, func)
+    }
+
+    /// Like `do_for_each_line`, on an input which was already read
+    pub fn do_for_each_line_of_str<F>(content: &str, mut func: F) -> Result<()>
+    where F: FnMut(&str) -> Result<()>
+    {
         for (line_idx, line) in content.lines().enumerate() {
             func(line).map_err(
                 |e| match e {
@@ -128,4 +228,27 @@ mod internal_common {
         }
         Ok(())
     }
-}
\ No newline at end of file
+
+    pub fn lcm(a: usize, b: usize) -> usize
+    {
+        let gcd = {
+            let (mut a, mut b) = (a, b);
+            while b != 0 {
+                (a, b) = (b, a % b);
+            }
+            a
+        };
+        a / gcd * b
+    }
+
+    /// Convert every char of a grid line into a cell, see `#[derive(GridCell)]`.
+    /// An unknown char gives a token error at its position in the line.
+    pub fn parse_cells<T>(line: &str) -> Result<Vec<T>>
+    where T: TryFrom<char, Error = Error>
+    {
+        line.chars().enumerate().map(|(i, c)| T::try_from(c).map_err(|e| match e {
+            Error::ParsingToken(token) => Error::new_token(i + token.line_pos, token.token_len),
+            _ => e
+        })).collect()
+    }
+}
@@ -0,0 +1,91 @@
+use crate::days::{Error, Result};
+
+pub const GLYPH_WIDTH: usize = 4;
+pub const GLYPH_HEIGHT: usize = 6;
+// glyphs are separated by one blank column
+const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+
+// letters of the 4x6 font used by the puzzles
+const ALPHABET: [(char, [&str; GLYPH_HEIGHT]); 16] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+/// Read the letters drawn with '#' on `rows`, every other char being blank.
+/// The error lists the first column of every glyph which is not a known letter.
+pub fn read_letters<S>(rows: &[S]) -> Result<String>
+where S: AsRef<str>
+{
+    let rows: Vec<Vec<bool>> = rows.iter().map(|row| row.as_ref().chars().map(|c| c == '#').collect()).collect();
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let num_glyphs = (width + 1) / CELL_WIDTH;
+
+    let mut letters = String::with_capacity(num_glyphs);
+    let mut unknown_columns = Vec::new();
+    for glyph_i in 0..num_glyphs {
+        let column = glyph_i * CELL_WIDTH;
+        match recognize(&rows, column) {
+            Some(letter) => letters.push(letter),
+            None => unknown_columns.push(column),
+        }
+    }
+    if !unknown_columns.is_empty() {
+        return Err(Error::UnknownGlyphs(unknown_columns));
+    }
+    Ok(letters)
+}
+
+fn recognize(rows: &[Vec<bool>], column: usize) -> Option<char>
+{
+    if rows.len() != GLYPH_HEIGHT {
+        return None;
+    }
+    let is_lit = |row: &Vec<bool>, x: usize| row.get(x).copied().unwrap_or(false);
+    ALPHABET.iter().find(|(_, glyph)| {
+        glyph.iter().zip(rows).all(|(glyph_row, row)| {
+            glyph_row.chars().enumerate().all(|(x, c)| (c == '#') == is_lit(row, column + x))
+        })
+    }).map(|(letter, _)| *letter)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // the glyphs of `word`, the same way a CRT would draw them
+    fn draw(word: &str) -> Vec<String> {
+        (0..GLYPH_HEIGHT).map(|y| {
+            word.chars().map(|letter| {
+                let (_, glyph) = ALPHABET.iter().find(|(l, _)| *l == letter).unwrap();
+                format!("{}.", glyph[y])
+            }).collect()
+        }).collect()
+    }
+
+    #[test]
+    fn test_read_letters() {
+        let word: String = ALPHABET.iter().map(|(letter, _)| letter).collect();
+        assert_eq!(read_letters(&draw(&word)).unwrap(), word);
+        assert_eq!(read_letters(&draw("HELLO")).unwrap(), "HELLO");
+
+        let mut rows = draw("FOOBAR");
+        rows[2].replace_range(6..7, "#");
+        rows[0].replace_range(25..29, "....");
+        let err = read_letters(&rows).unwrap_err();
+        assert!(matches!(err, Error::UnknownGlyphs(columns) if columns == vec![5, 25]));
+    }
+}
@@ -1,181 +1,611 @@
-use std::ops::{Add, Neg};
+use std::ops::{Add, Sub, Mul, Div, Neg, AddAssign, SubAssign, Deref, DerefMut, Index, IndexMut};
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Point2<T> {
-    pub x: T,
-    pub y: T
+/// A point (or vector) with `N` coordinates.
+/// `Point2`/`Point3`/`Point4` deref to views with named coordinates, so `p.x` works as a field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Point<T, const N: usize> {
+    pub coords: [T; N]
+}
+
+pub type Point2<T> = Point<T, 2>;
+pub type Point3<T> = Point<T, 3>;
+pub type Point4<T> = Point<T, 4>;
+
+impl<T, const N: usize> Point<T, N> {
+    pub const fn new(coords: [T; N]) -> Self {
+        Self {coords}
+    }
 }
 
 impl<T> Point2<T> {
-    pub fn from_xy(x: T, y: T) -> Self {
-        Self {x, y}
+    pub const fn from_xy(x: T, y: T) -> Self {
+        Self {coords: [x, y]}
     }
 }
 
-impl<T> Add for Point2<T> where T: Add<T, Output = T> {
-    type Output = Point2<T>;
-    fn add(self, rhs: Point2<T>) -> Self::Output {
-        Self::Output {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-        }
+impl<T> Point3<T> {
+    pub const fn from_xyz(x: T, y: T, z:T) -> Self {
+        Self {coords: [x, y, z]}
     }
 }
 
-impl<T> Add<&Self> for Point2<T> where T: Add<T, Output = T> + Copy {
-    type Output = Point2<T>;
-    fn add(self, rhs: &Point2<T>) -> Self::Output {
-        Self::Output {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-        }
+impl<T> Point4<T> {
+    pub const fn from_xyzw(x: T, y: T, z: T, w: T) -> Self {
+        Self {coords: [x, y, z, w]}
     }
 }
 
-impl<T> Add<Point2<T>> for &Point2<T> where T: Add<T, Output = T> + Copy {
-    type Output = Point2<T>;
-    fn add(self, rhs: Point2<T>) -> Self::Output {
-        Self::Output {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-        }
+impl<T, const N: usize> From<[T; N]> for Point<T, N> {
+    fn from(coords: [T; N]) -> Self {
+        Self {coords}
     }
 }
 
-impl<T> Add<Self> for &Point2<T> where T: Add<T, Output = T> + Copy {
-    type Output = Point2<T>;
-    fn add(self, rhs: Self) -> Self::Output {
-        Self::Output {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-        }
+impl<T, const N: usize> Index<usize> for Point<T, N> {
+    type Output = T;
+    fn index(&self, axis: usize) -> &T {
+        &self.coords[axis]
     }
 }
 
-impl<T> Neg for &Point2<T> where T: Neg<Output = T> + Copy {
-    type Output = Point2<T>;
+impl<T, const N: usize> IndexMut<usize> for Point<T, N> {
+    fn index_mut(&mut self, axis: usize) -> &mut T {
+        &mut self.coords[axis]
+    }
+}
 
-    fn neg(self) -> Self::Output {
-        Self::Output {
-            x: -self.x,
-            y: -self.y,
+// named views of the coordinates, see the Deref impls below
+#[repr(C)]
+pub struct XY<T> {
+    pub x: T,
+    pub y: T,
+}
+
+#[repr(C)]
+pub struct XYZ<T> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+#[repr(C)]
+pub struct XYZW<T> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+    pub w: T,
+}
+
+macro_rules! impl_named_coords {
+    ($N:literal, $View:ident) => {
+        impl<T> Deref for Point<T, $N> {
+            type Target = $View<T>;
+            fn deref(&self) -> &$View<T> {
+                // SAFETY: the view is repr(C) with exactly N fields of type T,
+                // so it has the same layout as [T; N]
+                unsafe { &*(self.coords.as_ptr() as *const $View<T>) }
+            }
+        }
+
+        impl<T> DerefMut for Point<T, $N> {
+            fn deref_mut(&mut self) -> &mut $View<T> {
+                // SAFETY: same as above
+                unsafe { &mut *(self.coords.as_mut_ptr() as *mut $View<T>) }
+            }
         }
+    };
+}
+impl_named_coords!(2, XY);
+impl_named_coords!(3, XYZ);
+impl_named_coords!(4, XYZW);
+
+/// Integer types usable as signed point coordinates
+pub trait Signed: Copy + PartialOrd + Add<Output = Self> + Sub<Output = Self> + Neg<Output = Self> {
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn abs(self) -> Self;
+    fn signum(self) -> Self;
+}
+
+macro_rules! impl_signed {
+    ($($Type:ty),*) => {
+        $(
+            impl Signed for $Type {
+                fn zero() -> Self { 0 }
+                fn one() -> Self { 1 }
+                fn abs(self) -> Self { <$Type>::abs(self) }
+                fn signum(self) -> Self { <$Type>::signum(self) }
+            }
+        )*
+    };
+}
+impl_signed!(i8, i16, i32, i64, i128, isize);
+
+fn abs_diff<T>(a: T, b: T) -> T
+where T: PartialOrd + Sub<Output = T>
+{
+    if a > b { a - b } else { b - a }
+}
+
+fn zip_map<T, U, const N: usize, F>(a: [T; N], b: [T; N], mut func: F) -> [U; N]
+where F: FnMut(T, T) -> U
+{
+    let mut b = b.into_iter();
+    a.map(|a| func(a, b.next().unwrap()))
+}
+
+// component-wise binary operator, for every combination of owned/borrowed operands
+macro_rules! impl_point_op {
+    ($Trait:ident, $func:ident) => {
+        impl<T, const N: usize> $Trait for Point<T, N> where T: $Trait<T, Output = T> {
+            type Output = Point<T, N>;
+            fn $func(self, rhs: Point<T, N>) -> Self::Output {
+                Point::new(zip_map(self.coords, rhs.coords, T::$func))
+            }
+        }
+
+        impl<T, const N: usize> $Trait<&Self> for Point<T, N> where T: $Trait<T, Output = T> + Copy {
+            type Output = Point<T, N>;
+            fn $func(self, rhs: &Point<T, N>) -> Self::Output {
+                Point::new(zip_map(self.coords, rhs.coords, T::$func))
+            }
+        }
+
+        impl<T, const N: usize> $Trait<Point<T, N>> for &Point<T, N> where T: $Trait<T, Output = T> + Copy {
+            type Output = Point<T, N>;
+            fn $func(self, rhs: Point<T, N>) -> Self::Output {
+                Point::new(zip_map(self.coords, rhs.coords, T::$func))
+            }
+        }
+
+        impl<T, const N: usize> $Trait<Self> for &Point<T, N> where T: $Trait<T, Output = T> + Copy {
+            type Output = Point<T, N>;
+            fn $func(self, rhs: Self) -> Self::Output {
+                Point::new(zip_map(self.coords, rhs.coords, T::$func))
+            }
+        }
+    };
+}
+
+// component-wise compound assignment, with owned or borrowed rhs
+macro_rules! impl_point_op_assign {
+    ($Trait:ident, $func:ident) => {
+        impl<T, const N: usize> $Trait for Point<T, N> where T: $Trait<T> {
+            fn $func(&mut self, rhs: Point<T, N>) {
+                for (a, b) in self.coords.iter_mut().zip(rhs.coords) {
+                    a.$func(b);
+                }
+            }
+        }
+
+        impl<T, const N: usize> $Trait<&Self> for Point<T, N> where T: $Trait<T> + Copy {
+            fn $func(&mut self, rhs: &Point<T, N>) {
+                for (a, b) in self.coords.iter_mut().zip(rhs.coords) {
+                    a.$func(b);
+                }
+            }
+        }
+    };
+}
+
+// multiplication or division of every component by a scalar
+macro_rules! impl_point_scalar_op {
+    ($Trait:ident, $func:ident) => {
+        impl<T, const N: usize> $Trait<T> for Point<T, N> where T: $Trait<T, Output = T> + Copy {
+            type Output = Point<T, N>;
+            fn $func(self, rhs: T) -> Self::Output {
+                Point::new(self.coords.map(|a| a.$func(rhs)))
+            }
+        }
+
+        impl<T, const N: usize> $Trait<T> for &Point<T, N> where T: $Trait<T, Output = T> + Copy {
+            type Output = Point<T, N>;
+            fn $func(self, rhs: T) -> Self::Output {
+                Point::new(self.coords.map(|a| a.$func(rhs)))
+            }
+        }
+    };
+}
+
+impl_point_op!(Add, add);
+impl_point_op!(Sub, sub);
+impl_point_op_assign!(AddAssign, add_assign);
+impl_point_op_assign!(SubAssign, sub_assign);
+impl_point_scalar_op!(Mul, mul);
+impl_point_scalar_op!(Div, div);
+
+impl<T, const N: usize> Neg for &Point<T, N> where T: Neg<Output = T> + Copy {
+    type Output = Point<T, N>;
+
+    fn neg(self) -> Self::Output {
+        Point::new(self.coords.map(T::neg))
     }
 }
 
-impl<T> Neg for Point2<T> where T: Neg<Output = T> {
-    type Output = Point2<T>;
+impl<T, const N: usize> Neg for Point<T, N> where T: Neg<Output = T> {
+    type Output = Point<T, N>;
 
     fn neg(self) -> Self::Output {
-        Self::Output {
-            x: -self.x,
-            y: -self.y,
-        }
+        Point::new(self.coords.map(T::neg))
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Point3<T> {
-    pub x: T,
-    pub y: T,
-    pub z: T,
+impl<T, const N: usize> Point<T, N> where T: Copy + Add<Output = T> + Mul<Output = T> {
+    pub fn dot(&self, other: &Self) -> T {
+        zip_map(self.coords, other.coords, T::mul).into_iter().reduce(T::add).unwrap()
+    }
 }
 
-impl<T> Point3<T> {
-    pub fn from_xyz(x: T, y: T, z:T) -> Self {
-        Self {x, y, z}
+impl<T, const N: usize> Point<T, N> where T: Copy + PartialOrd + Add<Output = T> + Sub<Output = T> {
+    pub fn manhattan_distance(&self, other: &Self) -> T {
+        zip_map(self.coords, other.coords, abs_diff).into_iter().reduce(T::add).unwrap()
+    }
+
+    pub fn chebyshev_distance(&self, other: &Self) -> T {
+        zip_map(self.coords, other.coords, abs_diff).into_iter()
+            .reduce(|a, b| if b > a { b } else { a }).unwrap()
     }
 }
 
-impl<T> Add for Point3<T> where T: Add<T, Output = T> {
-    type Output = Point3<T>;
-    fn add(self, rhs: Point3<T>) -> Self::Output {
-        Self::Output {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-            z: self.z + rhs.z,
-        }
+impl<T, const N: usize> Point<T, N> where T: Signed {
+    pub fn abs(&self) -> Self {
+        Self::new(self.coords.map(T::abs))
+    }
+
+    pub fn signum(&self) -> Self {
+        Self::new(self.coords.map(T::signum))
+    }
+
+    /// The 2*N points which differ from this one by 1 along a single axis
+    /// (edge neighbors in 2D, face neighbors in 3D)
+    pub fn axis_neighbors(&self) -> impl Iterator<Item = Self> {
+        let center = *self;
+        (0..N).flat_map(move |axis| [T::one(), -T::one()].into_iter().map(move |delta| {
+            let mut neigh = center;
+            neigh.coords[axis] = neigh.coords[axis] + delta;
+            neigh
+        }))
+    }
+
+    /// The 3^N - 1 points at a Chebyshev distance of 1 from this one
+    pub fn all_neighbors(&self) -> impl Iterator<Item = Self> {
+        let center = *self;
+        let num_neighbors = 3usize.pow(N as u32);
+        (0..num_neighbors).filter(move |&i| i != num_neighbors / 2).map(move |i| {
+            let mut neigh = center;
+            let mut digits = i;
+            for coord in neigh.coords.iter_mut() {
+                let delta = match digits % 3 {
+                    0 => -T::one(),
+                    1 => T::zero(),
+                    _ => T::one(),
+                };
+                *coord = *coord + delta;
+                digits /= 3;
+            }
+            neigh
+        })
     }
 }
 
-impl<T> Add<&Self> for Point3<T> where T: Add<T, Output = T> + Copy {
-    type Output = Point3<T>;
-    fn add(self, rhs: &Point3<T>) -> Self::Output {
-        Self::Output {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-            z: self.z + rhs.z,
+impl<T> Point2<T> where T: Signed {
+    /// Rotate by `quarter_turns` times 90°, counterclockwise with the y axis pointing up
+    /// (so clockwise on a grid where y points down).
+    /// Negative values rotate the other way.
+    pub fn rotate(&self, quarter_turns: i32) -> Self {
+        match quarter_turns.rem_euclid(4) {
+            0 => *self,
+            1 => Self::from_xy(-self.y, self.x),
+            2 => Self::from_xy(-self.x, -self.y),
+            _ => Self::from_xy(self.y, -self.x),
         }
     }
 }
 
-impl<T> Add<Point3<T>> for &Point3<T> where T: Add<T, Output = T> + Copy {
-    type Output = Point3<T>;
-    fn add(self, rhs: Point3<T>) -> Self::Output {
-        Self::Output {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-            z: self.z + rhs.z,
-        }
+impl<T> Point3<T> where T: Copy + Sub<Output = T> + Mul<Output = T> {
+    pub fn cross(&self, other: &Self) -> Self {
+        Self::from_xyz(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
     }
 }
 
-impl<T> Add<Self> for &Point3<T> where T: Add<T, Output = T> + Copy {
-    type Output = Point3<T>;
-    fn add(self, rhs: Self) -> Self::Output {
-        Self::Output {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-            z: self.z + rhs.z,
+impl<T> Point3<T> where T: Signed {
+    /// Rotate by `quarter_turns` times 90° around the x axis (right-handed)
+    pub fn rotate_x(&self, quarter_turns: i32) -> Self {
+        match quarter_turns.rem_euclid(4) {
+            0 => *self,
+            1 => Self::from_xyz(self.x, -self.z, self.y),
+            2 => Self::from_xyz(self.x, -self.y, -self.z),
+            _ => Self::from_xyz(self.x, self.z, -self.y),
+        }
+    }
+
+    /// Rotate by `quarter_turns` times 90° around the y axis (right-handed)
+    pub fn rotate_y(&self, quarter_turns: i32) -> Self {
+        match quarter_turns.rem_euclid(4) {
+            0 => *self,
+            1 => Self::from_xyz(self.z, self.y, -self.x),
+            2 => Self::from_xyz(-self.x, self.y, -self.z),
+            _ => Self::from_xyz(-self.z, self.y, self.x),
+        }
+    }
+
+    /// Rotate by `quarter_turns` times 90° around the z axis (right-handed)
+    pub fn rotate_z(&self, quarter_turns: i32) -> Self {
+        match quarter_turns.rem_euclid(4) {
+            0 => *self,
+            1 => Self::from_xyz(-self.y, self.x, self.z),
+            2 => Self::from_xyz(-self.x, -self.y, self.z),
+            _ => Self::from_xyz(self.y, -self.x, self.z),
         }
     }
 }
 
-impl<T> Neg for &Point3<T> where T: Neg<Output = T> + Copy {
-    type Output = Point3<T>;
+/// Smallest axis-aligned box containing a set of points, bounds are inclusive
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BoundingBox<T, const N: usize> {
+    pub min: Point<T, N>,
+    pub max: Point<T, N>,
+}
 
-    fn neg(self) -> Self::Output {
-        Self::Output {
-            x: -self.x,
-            y: -self.y,
-            z: -self.z,
+impl<T, const N: usize> BoundingBox<T, N> where T: Copy + PartialOrd {
+    pub fn from_point(point: &Point<T, N>) -> Self {
+        Self {min: *point, max: *point}
+    }
+
+    /// None if there are no points
+    pub fn from_points<'a, I>(points: I) -> Option<Self>
+    where I: IntoIterator<Item = &'a Point<T, N>>, T: 'a
+    {
+        let mut points = points.into_iter();
+        let mut ret = Self::from_point(points.next()?);
+        for point in points {
+            ret.update(point);
         }
+        Some(ret)
+    }
+
+    pub fn update(&mut self, point: &Point<T, N>) {
+        for axis in 0..N {
+            if point[axis] < self.min[axis] {
+                self.min[axis] = point[axis];
+            }
+            if point[axis] > self.max[axis] {
+                self.max[axis] = point[axis];
+            }
+        }
+    }
+
+    pub fn contains(&self, point: &Point<T, N>) -> bool {
+        (0..N).all(|axis| self.min[axis] <= point[axis] && point[axis] <= self.max[axis])
     }
 }
 
-impl<T> Neg for Point3<T> where T: Neg<Output = T> {
-    type Output = Point3<T>;
+impl<T, const N: usize> BoundingBox<T, N> where T: Signed + Mul<Output = T> {
+    /// Number of points along each axis
+    pub fn size(&self) -> Point<T, N> {
+        Point::new(zip_map(self.max.coords, self.min.coords, |max, min| max - min + T::one()))
+    }
 
-    fn neg(self) -> Self::Output {
-        Self::Output {
-            x: -self.x,
-            y: -self.y,
-            z: -self.z,
+    /// Number of points inside the box (area in 2D, volume in 3D)
+    pub fn volume(&self) -> T {
+        self.size().coords.into_iter().fold(T::one(), |acc, len| acc * len)
+    }
+
+    pub fn grow(&self, margin: T) -> Self {
+        Self {
+            min: Point::new(self.min.coords.map(|c| c - margin)),
+            max: Point::new(self.max.coords.map(|c| c + margin)),
         }
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::op_ref)] // every owned/borrowed operand combination is exercised on purpose
 mod test {
     use super::*;
 
     #[test]
     fn test_add_point2() {
-        let a = Point2 {x: 1, y: 1};
-        let b = Point2 {x: -1, y: 1};
+        let a = Point2::from_xy(1, 1);
+        let b = Point2::from_xy(-1, 1);
         let c = &a + &b;
-        assert_eq!(c, a.clone() + &b);
-        assert_eq!(c, &a + b.clone());
-        assert_eq!(c, a.clone() + b.clone());
+        assert_eq!(c, a + &b);
+        assert_eq!(c, &a + b);
+        assert_eq!(c, a + b);
     }
 
     #[test]
     fn test_add_point3() {
-        let a = Point3 {x: 1, y: 1, z: 1};
-        let b = Point3 {x: -1, y: 1, z: 2};
+        let a = Point3::from_xyz(1, 1, 1);
+        let b = Point3::from_xyz(-1, 1, 2);
         let c = &a + &b;
-        assert_eq!(c, a.clone() + &b);
-        assert_eq!(c, &a + b.clone());
-        assert_eq!(c, a.clone() + b.clone());
+        assert_eq!(c, a + &b);
+        assert_eq!(c, &a + b);
+        assert_eq!(c, a + b);
+    }
+
+    #[test]
+    fn test_sub_point2() {
+        let a = Point2::from_xy(1, 1);
+        let b = Point2::from_xy(-1, 3);
+        let c = &a - &b;
+        assert_eq!(c, Point2::from_xy(2, -2));
+        assert_eq!(c, a - &b);
+        assert_eq!(c, &a - b);
+        assert_eq!(c, a - b);
+        assert_eq!(c + b, a);
+    }
+
+    #[test]
+    fn test_sub_point3() {
+        let a = Point3::from_xyz(1, 1, 1);
+        let b = Point3::from_xyz(-1, 1, 2);
+        let c = &a - &b;
+        assert_eq!(c, Point3::from_xyz(2, 0, -1));
+        assert_eq!(c, a - &b);
+        assert_eq!(c, &a - b);
+        assert_eq!(c, a - b);
+    }
+
+    #[test]
+    fn test_assign_ops() {
+        let mut a = Point2::from_xy(1, 2);
+        a += Point2::from_xy(3, 4);
+        assert_eq!(a, Point2::from_xy(4, 6));
+        a -= &Point2::from_xy(1, 1);
+        assert_eq!(a, Point2::from_xy(3, 5));
+
+        let mut b = Point3::from_xyz(1, 2, 3);
+        b += &Point3::from_xyz(1, 1, 1);
+        b -= Point3::from_xyz(0, 0, 4);
+        assert_eq!(b, Point3::from_xyz(2, 3, 0));
+    }
+
+    #[test]
+    fn test_scalar_ops() {
+        let a = Point2::from_xy(2, -4);
+        assert_eq!(a * 3, Point2::from_xy(6, -12));
+        assert_eq!(&a / 2, Point2::from_xy(1, -2));
+
+        let b = Point3::from_xyz(2, -4, 6);
+        assert_eq!(&b * -1, -b);
+        assert_eq!(b / 2, Point3::from_xyz(1, -2, 3));
+    }
+
+    #[test]
+    fn test_dot_cross() {
+        let a = Point2::from_xy(1, 2);
+        let b = Point2::from_xy(3, -4);
+        assert_eq!(a.dot(&b), -5);
+
+        let x = Point3::from_xyz(1, 0, 0);
+        let y = Point3::from_xyz(0, 1, 0);
+        let z = Point3::from_xyz(0, 0, 1);
+        assert_eq!(x.cross(&y), z);
+        assert_eq!(y.cross(&z), x);
+        assert_eq!(z.cross(&x), y);
+        assert_eq!(y.cross(&x), -z);
+        assert_eq!(x.dot(&y), 0);
+        assert_eq!(Point3::from_xyz(1, 2, 3).dot(&Point3::from_xyz(4, -5, 6)), 12);
+    }
+
+    #[test]
+    fn test_distances() {
+        let a = Point2::from_xy(8, 7);
+        let b = Point2::from_xy(2, 10);
+        assert_eq!(a.manhattan_distance(&b), 9);
+        assert_eq!(b.manhattan_distance(&a), 9);
+        assert_eq!(a.chebyshev_distance(&b), 6);
+
+        let c = Point2::<u32>::from_xy(500, 0);
+        let d = Point2::<u32>::from_xy(494, 9);
+        assert_eq!(c.manhattan_distance(&d), 15);
+
+        let e = Point3::from_xyz(1, -2, 3);
+        let f = Point3::from_xyz(-1, 2, 3);
+        assert_eq!(e.manhattan_distance(&f), 6);
+        assert_eq!(e.chebyshev_distance(&f), 4);
+    }
+
+    #[test]
+    fn test_abs_signum() {
+        let a = Point2::from_xy(-3, 0);
+        assert_eq!(a.abs(), Point2::from_xy(3, 0));
+        assert_eq!(a.signum(), Point2::from_xy(-1, 0));
+
+        let b = Point3::from_xyz(-3, 7, 0);
+        assert_eq!(b.abs(), Point3::from_xyz(3, 7, 0));
+        assert_eq!(b.signum(), Point3::from_xyz(-1, 1, 0));
+    }
+
+    #[test]
+    fn test_rotate_point2() {
+        let a = Point2::from_xy(1, 0);
+        assert_eq!(a.rotate(1), Point2::from_xy(0, 1));
+        assert_eq!(a.rotate(2), Point2::from_xy(-1, 0));
+        assert_eq!(a.rotate(-1), Point2::from_xy(0, -1));
+        assert_eq!(a.rotate(4), a);
+        let b = Point2::from_xy(2, 5);
+        assert_eq!(b.rotate(1).rotate(-1), b);
+        assert_eq!(b.rotate(1).rotate(1), b.rotate(2));
+    }
+
+    #[test]
+    fn test_rotate_point3() {
+        let x = Point3::from_xyz(1, 0, 0);
+        let y = Point3::from_xyz(0, 1, 0);
+        let z = Point3::from_xyz(0, 0, 1);
+        assert_eq!(y.rotate_x(1), z);
+        assert_eq!(z.rotate_y(1), x);
+        assert_eq!(x.rotate_z(1), y);
+        assert_eq!(x.rotate_x(1), x);
+
+        let p = Point3::from_xyz(1, 2, 3);
+        for quarter_turns in -4..=4 {
+            assert_eq!(p.rotate_x(quarter_turns).rotate_x(-quarter_turns), p);
+            assert_eq!(p.rotate_y(quarter_turns).rotate_y(-quarter_turns), p);
+            assert_eq!(p.rotate_z(quarter_turns).rotate_z(-quarter_turns), p);
+            assert_eq!(p.rotate_z(quarter_turns).manhattan_distance(&Point3::from_xyz(0, 0, 0)), 6);
+        }
+        assert_eq!(p.rotate_y(2), p.rotate_y(1).rotate_y(1));
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let a = Point2::from_xy(0, 0);
+        let n4: Vec<_> = a.axis_neighbors().collect();
+        assert_eq!(n4.len(), 4);
+        assert!(n4.iter().all(|n| n.manhattan_distance(&a) == 1));
+        let n8: Vec<_> = a.all_neighbors().collect();
+        assert_eq!(n8.len(), 8);
+        assert!(n8.iter().all(|n| n.chebyshev_distance(&a) == 1));
+
+        let b = Point3::from_xyz(1, 2, 3);
+        let n6: Vec<_> = b.axis_neighbors().collect();
+        assert_eq!(n6.len(), 6);
+        assert!(n6.iter().all(|n| n.manhattan_distance(&b) == 1));
+        let n26: std::collections::HashSet<_> = b.all_neighbors().collect();
+        assert_eq!(n26.len(), 26);
+        assert!(n26.iter().all(|n| n.chebyshev_distance(&b) == 1));
+
+        let c = Point4::from_xyzw(0, 0, 0, 0);
+        assert_eq!(c.axis_neighbors().count(), 8);
+        let n80: std::collections::HashSet<_> = c.all_neighbors().collect();
+        assert_eq!(n80.len(), 80);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_named_coords() {
+        let mut a = Point2::from_xy(1, 2);
+        a.x += 10;
+        a[1] -= 1;
+        assert_eq!((a.x, a.y), (11, 1));
+        assert_eq!(a, Point::new([11, 1]));
+
+        let mut b = Point4::from([1, 2, 3, 4]);
+        b.w = 0;
+        assert_eq!((b.x, b.y, b.z, b.w), (1, 2, 3, 0));
+        assert_eq!(b.coords, [1, 2, 3, 0]);
+
+        // ordering is lexicographic, x first
+        assert!(Point2::from_xy(0, 5) < Point2::from_xy(1, 0));
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let points = [Point3::from_xyz(1, -2, 3), Point3::from_xyz(0, 4, 3), Point3::from_xyz(2, 0, 1)];
+        let bb = BoundingBox::from_points(&points).unwrap();
+        assert_eq!(bb.min, Point3::from_xyz(0, -2, 1));
+        assert_eq!(bb.max, Point3::from_xyz(2, 4, 3));
+        assert_eq!(bb.size(), Point3::from_xyz(3, 7, 3));
+        assert_eq!(bb.volume(), 63);
+        assert!(points.iter().all(|p| bb.contains(p)));
+        assert!(!bb.contains(&Point3::from_xyz(3, 0, 1)));
+        assert_eq!(bb.grow(1).volume(), 5 * 9 * 5);
+        assert!(BoundingBox::<i32, 2>::from_points(&[]).is_none());
+    }
+}
@@ -0,0 +1,223 @@
+use std::ops::{Add, Sub, Range};
+
+/// Types usable as `RangeSet` bounds, i.e. integers
+pub trait Integer: Copy + Ord + Default + Add<Output = Self> + Sub<Output = Self> {}
+
+impl<T> Integer for T
+where T: Copy + Ord + Default + Add<Output = T> + Sub<Output = T> {}
+
+/// Set of integers stored as sorted half-open intervals.
+/// Intervals are kept normalized: non-empty, and neither overlapping nor touching each other.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RangeSet<T> {
+    ranges: Vec<Range<T>>,
+}
+
+impl<T> RangeSet<T>
+where T: Integer
+{
+    pub fn new() -> Self
+    {
+        Self { ranges: Vec::new() }
+    }
+
+    pub fn ranges(&self) -> &[Range<T>]
+    {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.ranges.is_empty()
+    }
+
+    /// Number of integers in the set
+    pub fn total_len(&self) -> T
+    {
+        self.ranges.iter().fold(T::default(), |acc, range| acc + (range.end - range.start))
+    }
+
+    pub fn insert(&mut self, range: Range<T>)
+    {
+        if range.is_empty() {
+            return;
+        }
+        // touching ranges are merged too
+        let lo = self.ranges.partition_point(|r| r.end < range.start);
+        let hi = self.ranges.partition_point(|r| r.start <= range.end);
+        let mut merged = range;
+        if lo < hi {
+            merged.start = merged.start.min(self.ranges[lo].start);
+            merged.end = merged.end.max(self.ranges[hi - 1].end);
+        }
+        self.ranges.splice(lo..hi, [merged]);
+    }
+
+    pub fn remove(&mut self, range: Range<T>)
+    {
+        if range.is_empty() {
+            return;
+        }
+        let lo = self.ranges.partition_point(|r| r.end <= range.start);
+        let hi = self.ranges.partition_point(|r| r.start < range.end);
+        if lo == hi {
+            return;
+        }
+        let left = self.ranges[lo].start..range.start;
+        let right = range.end..self.ranges[hi - 1].end;
+        self.ranges.splice(lo..hi, [left, right].into_iter().filter(|r| !r.is_empty()));
+    }
+
+    pub fn union(&self, other: &Self) -> Self
+    {
+        let mut ret = self.clone();
+        for range in &other.ranges {
+            ret.insert(range.clone());
+        }
+        ret
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self
+    {
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a, b) = (&self.ranges[i], &other.ranges[j]);
+            let common = a.start.max(b.start)..a.end.min(b.end);
+            if !common.is_empty() {
+                ranges.push(common);
+            }
+            if a.end < b.end {
+                i += 1;
+            }
+            else {
+                j += 1;
+            }
+        }
+        Self { ranges }
+    }
+
+    /// Integers of `bounds` which are not in the set
+    pub fn complement(&self, bounds: Range<T>) -> Self
+    {
+        let mut ret = Self::from(bounds);
+        for range in &self.ranges {
+            ret.remove(range.clone());
+        }
+        ret
+    }
+
+    /// Holes between the first and the last interval
+    pub fn gaps(&self) -> impl Iterator<Item = Range<T>> + '_
+    {
+        self.ranges.windows(2).map(|w| w[0].end..w[1].start)
+    }
+
+    pub fn contains(&self, value: T) -> bool
+    {
+        let idx = self.ranges.partition_point(|r| r.end <= value);
+        self.ranges.get(idx).is_some_and(|r| r.start <= value)
+    }
+
+    /// True if every integer of `range` is in the set
+    pub fn contains_range(&self, range: &Range<T>) -> bool
+    {
+        if range.is_empty() {
+            return true;
+        }
+        let idx = self.ranges.partition_point(|r| r.end <= range.start);
+        self.ranges.get(idx).is_some_and(|r| r.start <= range.start && range.end <= r.end)
+    }
+
+    /// True if at least one integer of `range` is in the set
+    pub fn overlaps(&self, range: &Range<T>) -> bool
+    {
+        let idx = self.ranges.partition_point(|r| r.end <= range.start);
+        self.ranges.get(idx).is_some_and(|r| r.start < range.end && !range.is_empty())
+    }
+}
+
+impl<T: Integer> From<Range<T>> for RangeSet<T> {
+    fn from(range: Range<T>) -> Self
+    {
+        let mut ret = Self::new();
+        ret.insert(range);
+        ret
+    }
+}
+
+impl<T: Integer> FromIterator<Range<T>> for RangeSet<T> {
+    fn from_iter<I: IntoIterator<Item = Range<T>>>(iter: I) -> Self
+    {
+        let mut ret = Self::new();
+        for range in iter {
+            ret.insert(range);
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_insert() {
+        let mut set = RangeSet::new();
+        set.insert(5..8);
+        set.insert(1..3);
+        set.insert(10..10);
+        assert_eq!(set.ranges(), &[1..3, 5..8]);
+        set.insert(3..4);
+        assert_eq!(set.ranges(), &[1..4, 5..8]);
+        set.insert(2..6);
+        assert_eq!(set, RangeSet::from(1..8));
+        set.insert(-3..-1);
+        set.insert(0..20);
+        assert_eq!(set.ranges(), &[-3..-1, 0..20]);
+        assert_eq!(set.total_len(), 22);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut set: RangeSet<u32> = [0..10, 20..30].into_iter().collect();
+        set.remove(3..5);
+        assert_eq!(set.ranges(), &[0..3, 5..10, 20..30]);
+        set.remove(8..25);
+        assert_eq!(set.ranges(), &[0..3, 5..8, 25..30]);
+        set.remove(0..3);
+        set.remove(30..40);
+        assert_eq!(set.ranges(), &[5..8, 25..30]);
+        set.remove(0..100);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a: RangeSet<i64> = [0..5, 10..15].into_iter().collect();
+        let b: RangeSet<i64> = [3..12, 14..20].into_iter().collect();
+        assert_eq!(a.union(&b), RangeSet::from(0..20));
+        assert_eq!(a.intersection(&b).ranges(), &[3..5, 10..12, 14..15]);
+        assert_eq!(a.complement(-2..12).ranges(), &[-2..0, 5..10]);
+        assert_eq!(a.gaps().collect::<Vec<_>>(), vec![5..10]);
+        assert_eq!(b.complement(0..30).intersection(&b), RangeSet::new());
+    }
+
+    #[test]
+    fn test_queries() {
+        let set: RangeSet<u8> = [2..5, 8..9].into_iter().collect();
+        assert!(!set.contains(1));
+        assert!(set.contains(2));
+        assert!(set.contains(4));
+        assert!(!set.contains(5));
+        assert!(set.contains(8));
+        assert!(set.contains_range(&(2..5)));
+        assert!(set.contains_range(&(3..4)));
+        assert!(!set.contains_range(&(3..6)));
+        assert!(!set.contains_range(&(4..9)));
+        assert!(set.overlaps(&(0..3)));
+        assert!(set.overlaps(&(4..8)));
+        assert!(!set.overlaps(&(5..8)));
+        assert!(!set.overlaps(&(9..200)));
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::ops::Mul;
+use crate::days::points::{Point, BoundingBox, Signed};
+use crate::days::search;
+
+/// Every state reachable from `starts` (included) by following `neighbors`
+pub fn flood_fill<S, Starts, Neighbors, NeighborsIter>(starts: Starts, neighbors: Neighbors) -> HashSet<S>
+where S: Clone + Eq + Hash,
+Starts: IntoIterator<Item = S>,
+Neighbors: FnMut(&S) -> NeighborsIter,
+NeighborsIter: IntoIterator<Item = S>
+{
+    search::bfs(starts, neighbors, |_| false).costs.into_keys().collect()
+}
+
+/// Partition of a set of states into connected components
+#[derive(Debug, Clone)]
+pub struct Components<S> {
+    /// Component id of every state
+    pub labels: HashMap<S, usize>,
+    /// States of every component, indexed by component id
+    pub members: Vec<Vec<S>>,
+}
+
+/// Label the connected components of `states`.
+/// `neighbors` must only yield states connected to its argument, and be symmetric.
+pub fn connected_components<S, States, Neighbors, NeighborsIter>(states: States, mut neighbors: Neighbors) -> Components<S>
+where S: Clone + Eq + Hash,
+States: IntoIterator<Item = S>,
+Neighbors: FnMut(&S) -> NeighborsIter,
+NeighborsIter: IntoIterator<Item = S>
+{
+    let mut components = Components {
+        labels: HashMap::new(),
+        members: Vec::new(),
+    };
+    for state in states {
+        if components.labels.contains_key(&state) {
+            continue;
+        }
+        let id = components.members.len();
+        let members: Vec<S> = flood_fill([state], &mut neighbors).into_iter().collect();
+        for member in &members {
+            components.labels.insert(member.clone(), id);
+        }
+        components.members.push(members);
+    }
+    components
+}
+
+impl<S> Components<S>
+where S: Eq + Hash
+{
+    pub fn num_components(&self) -> usize
+    {
+        self.members.len()
+    }
+
+    pub fn component_of(&self, state: &S) -> Option<usize>
+    {
+        self.labels.get(state).copied()
+    }
+
+    pub fn sizes(&self) -> Vec<usize>
+    {
+        self.members.iter().map(|members| members.len()).collect()
+    }
+
+    /// Number of (member, adjacent state) pairs where the adjacent state is outside of the component.
+    /// `adjacency` gives every state touching its argument, whatever the component.
+    pub fn boundary_count<Adjacency, AdjacencyIter>(&self, id: usize, mut adjacency: Adjacency) -> usize
+    where Adjacency: FnMut(&S) -> AdjacencyIter,
+    AdjacencyIter: IntoIterator<Item = S>
+    {
+        self.members[id].iter().map(
+            |member| adjacency(member).into_iter().filter(|adj| self.component_of(adj) != Some(id)).count()
+        ).sum()
+    }
+}
+
+impl<T, const N: usize> Components<Point<T, N>>
+where T: Signed + Mul<Output = T> + Eq + Hash
+{
+    pub fn bounding_boxes(&self) -> Vec<BoundingBox<T, N>>
+    {
+        self.members.iter().map(|members| BoundingBox::from_points(members).unwrap()).collect()
+    }
+
+    /// Number of unit faces (edges in 2D) between each component and the outside
+    pub fn surface_areas(&self) -> Vec<usize>
+    {
+        (0..self.num_components()).map(|id| self.boundary_count(id, |p| p.axis_neighbors())).collect()
+    }
+}
+
+/// Number of unit faces of `cells` which do not touch another cell
+pub fn surface_area<T, const N: usize>(cells: &HashSet<Point<T, N>>) -> usize
+where T: Signed + Eq + Hash
+{
+    cells.iter().map(|p| p.axis_neighbors().filter(|n| !cells.contains(n)).count()).sum()
+}
+
+/// Points around `cells` which can be reached from far away without going through a cell.
+/// The search is limited to the bounding box of the cells, grown by 1.
+pub fn exterior<T, const N: usize>(cells: &HashSet<Point<T, N>>) -> HashSet<Point<T, N>>
+where T: Signed + Mul<Output = T> + Eq + Hash
+{
+    let bounding_box = match BoundingBox::from_points(cells) {
+        Some(bounding_box) => bounding_box.grow(T::one()),
+        None => return HashSet::new(),
+    };
+    flood_fill([bounding_box.min], |p| p.axis_neighbors()
+        .filter(|n| bounding_box.contains(n) && !cells.contains(n))
+        .collect::<Vec<_>>())
+}
+
+/// Like `surface_area`, ignoring the faces of enclosed pockets
+pub fn exterior_surface_area<T, const N: usize>(cells: &HashSet<Point<T, N>>) -> usize
+where T: Signed + Mul<Output = T> + Eq + Hash
+{
+    let exterior = exterior(cells);
+    cells.iter().map(|p| p.axis_neighbors().filter(|n| exterior.contains(n)).count()).sum()
+}
+
+/// Connected pockets of empty space fully enclosed by `cells`
+pub fn enclosed_pockets<T, const N: usize>(cells: &HashSet<Point<T, N>>) -> Components<Point<T, N>>
+where T: Signed + Mul<Output = T> + Eq + Hash
+{
+    let exterior = exterior(cells);
+    let is_pocket = |p: &Point<T, N>| !cells.contains(p) && !exterior.contains(p);
+    let candidates = cells.iter()
+        .flat_map(|p| p.axis_neighbors())
+        .filter(is_pocket)
+        .collect::<Vec<_>>();
+    connected_components(candidates, |p| p.axis_neighbors().filter(is_pocket).collect::<Vec<_>>())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::days::points::{Point2, Point3};
+
+    fn parse_2d(lines: &[&str]) -> HashSet<Point2<i32>> {
+        let mut cells = HashSet::new();
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                if c == '#' {
+                    cells.insert(Point2::from_xy(x as i32, y as i32));
+                }
+            }
+        }
+        cells
+    }
+
+    #[test]
+    fn test_components() {
+        let cells = parse_2d(&[
+            "##..#",
+            "#...#",
+            "...##",
+            "#....",
+        ]);
+        let components = connected_components(
+            cells.iter().copied(),
+            |p| p.axis_neighbors().filter(|n| cells.contains(n)).collect::<Vec<_>>()
+        );
+        assert_eq!(components.num_components(), 3);
+        let mut sizes = components.sizes();
+        sizes.sort();
+        assert_eq!(sizes, vec![1, 3, 4]);
+
+        let id = components.component_of(&Point2::from_xy(4, 0)).unwrap();
+        assert_eq!(components.component_of(&Point2::from_xy(3, 2)), Some(id));
+        assert_eq!(components.component_of(&Point2::from_xy(1, 1)), None);
+        assert_eq!(components.bounding_boxes()[id].min, Point2::from_xy(3, 0));
+        assert_eq!(components.bounding_boxes()[id].max, Point2::from_xy(4, 2));
+        assert_eq!(components.surface_areas()[id], 10);
+
+        let id = components.component_of(&Point2::from_xy(0, 3)).unwrap();
+        assert_eq!(components.sizes()[id], 1);
+        assert_eq!(components.surface_areas()[id], 4);
+        // diagonal adjacency only
+        assert_eq!(components.boundary_count(id, |p| p.all_neighbors().filter(|n| n.x != p.x && n.y != p.y).collect::<Vec<_>>()), 4);
+    }
+
+    #[test]
+    fn test_flood_fill_and_pockets() {
+        let cells = parse_2d(&[
+            "#####...",
+            "#...#.#.",
+            "#####.#.",
+            "......#.",
+            "..###.#.",
+            "..#.#...",
+            "..###...",
+        ]);
+        let outside = flood_fill([Point2::from_xy(7, 0)],
+            |p| p.axis_neighbors().filter(|n| !cells.contains(n) && (0..8).contains(&n.x) && (0..7).contains(&n.y)).collect::<Vec<_>>());
+        assert!(!outside.contains(&Point2::from_xy(1, 1)));
+        assert!(!outside.contains(&Point2::from_xy(3, 5)));
+        assert!(outside.contains(&Point2::from_xy(0, 6)));
+
+        let pockets = enclosed_pockets(&cells);
+        assert_eq!(pockets.num_components(), 2);
+        let mut sizes = pockets.sizes();
+        sizes.sort();
+        assert_eq!(sizes, vec![1, 3]);
+
+        assert_eq!(surface_area(&cells), (16 + 8) + 10 + (12 + 4));
+        assert_eq!(exterior_surface_area(&cells), 16 + 10 + 12);
+    }
+
+    #[test]
+    fn test_3d_shell() {
+        let center = Point3::from_xyz(0, 0, 0);
+        let cells: HashSet<_> = center.all_neighbors().collect();
+        assert_eq!(surface_area(&cells), 9 * 6 + 6);
+        assert_eq!(exterior_surface_area(&cells), 9 * 6);
+        let pockets = enclosed_pockets(&cells);
+        assert_eq!(pockets.members, vec![vec![center]]);
+        assert_eq!(pockets.surface_areas(), vec![6]);
+    }
+}
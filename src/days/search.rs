@@ -0,0 +1,265 @@
+use std::collections::{HashMap, VecDeque, BinaryHeap};
+use std::cmp::Ordering;
+use std::hash::Hash;
+use std::ops::Add;
+
+/// Outcome of a graph search.
+/// Only the states which were reached before the search stopped are in `costs`.
+#[derive(Debug, Clone)]
+pub struct SearchResult<S, C> {
+    pub costs: HashMap<S, C>,
+    pub predecessors: HashMap<S, S>,
+    /// First state satisfying the goal predicate, if the search stopped on one
+    pub goal: Option<S>,
+}
+
+impl<S, C> SearchResult<S, C>
+where S: Clone + Eq + Hash, C: Copy
+{
+    pub fn cost(&self, state: &S) -> Option<C>
+    {
+        self.costs.get(state).copied()
+    }
+
+    pub fn goal_cost(&self) -> Option<C>
+    {
+        self.goal.as_ref().and_then(|goal| self.cost(goal))
+    }
+
+    /// States from one of the starts to `state` (both included), or None if it was not reached
+    pub fn path_to(&self, state: &S) -> Option<Vec<S>>
+    {
+        if !self.costs.contains_key(state) {
+            return None;
+        }
+        let mut path = vec![state.clone()];
+        let mut current = state;
+        while let Some(pred) = self.predecessors.get(current) {
+            path.push(pred.clone());
+            current = pred;
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+/// Breadth-first search where every move costs 1.
+/// Stops as soon as a state satisfying `is_goal` is reached (pass `|_| false` to explore everything).
+pub fn bfs<S, Starts, Neighbors, NeighborsIter, Goal>(
+    starts: Starts,
+    mut neighbors: Neighbors,
+    mut is_goal: Goal) -> SearchResult<S, usize>
+where S: Clone + Eq + Hash,
+Starts: IntoIterator<Item = S>,
+Neighbors: FnMut(&S) -> NeighborsIter,
+NeighborsIter: IntoIterator<Item = S>,
+Goal: FnMut(&S) -> bool
+{
+    let mut result = SearchResult {
+        costs: HashMap::new(),
+        predecessors: HashMap::new(),
+        goal: None,
+    };
+    let mut to_visit: VecDeque<S> = VecDeque::new();
+    for start in starts {
+        if result.costs.insert(start.clone(), 0).is_none() {
+            to_visit.push_back(start);
+        }
+    }
+
+    while let Some(current) = to_visit.pop_front() {
+        if is_goal(&current) {
+            result.goal = Some(current);
+            break;
+        }
+        let next_cost = result.costs[&current] + 1;
+        for neigh in neighbors(&current) {
+            if result.costs.contains_key(&neigh) {
+                continue;
+            }
+            result.costs.insert(neigh.clone(), next_cost);
+            result.predecessors.insert(neigh.clone(), current.clone());
+            to_visit.push_back(neigh);
+        }
+    }
+
+    result
+}
+
+/// Shortest paths with non-negative move costs, `neighbors` yields (state, move cost) pairs.
+/// Stops as soon as a state satisfying `is_goal` is settled.
+pub fn dijkstra<S, C, Starts, Neighbors, NeighborsIter, Goal>(
+    starts: Starts,
+    neighbors: Neighbors,
+    is_goal: Goal) -> SearchResult<S, C>
+where S: Clone + Eq + Hash,
+C: Copy + Ord + Default + Add<Output = C>,
+Starts: IntoIterator<Item = S>,
+Neighbors: FnMut(&S) -> NeighborsIter,
+NeighborsIter: IntoIterator<Item = (S, C)>,
+Goal: FnMut(&S) -> bool
+{
+    a_star(starts, neighbors, |_| C::default(), is_goal)
+}
+
+/// Like `dijkstra`, with states explored in order of cost + `heuristic`.
+/// The heuristic must never overestimate the remaining cost to a goal for the result to be optimal.
+pub fn a_star<S, C, Starts, Neighbors, NeighborsIter, Heuristic, Goal>(
+    starts: Starts,
+    mut neighbors: Neighbors,
+    mut heuristic: Heuristic,
+    mut is_goal: Goal) -> SearchResult<S, C>
+where S: Clone + Eq + Hash,
+C: Copy + Ord + Default + Add<Output = C>,
+Starts: IntoIterator<Item = S>,
+Neighbors: FnMut(&S) -> NeighborsIter,
+NeighborsIter: IntoIterator<Item = (S, C)>,
+Heuristic: FnMut(&S) -> C,
+Goal: FnMut(&S) -> bool
+{
+    let mut result = SearchResult {
+        costs: HashMap::new(),
+        predecessors: HashMap::new(),
+        goal: None,
+    };
+    let mut to_visit: BinaryHeap<HeapEntry<S, C>> = BinaryHeap::new();
+    for start in starts {
+        if result.costs.insert(start.clone(), C::default()).is_none() {
+            to_visit.push(HeapEntry {priority: heuristic(&start), cost: C::default(), state: start});
+        }
+    }
+
+    while let Some(HeapEntry {cost, state: current, ..}) = to_visit.pop() {
+        if cost > result.costs[&current] {
+            // outdated entry, the state was pushed again with a lower cost
+            continue;
+        }
+        if is_goal(&current) {
+            result.goal = Some(current);
+            break;
+        }
+        for (neigh, move_cost) in neighbors(&current) {
+            let next_cost = cost + move_cost;
+            if let Some(&known_cost) = result.costs.get(&neigh) {
+                if known_cost <= next_cost {
+                    continue;
+                }
+            }
+            result.costs.insert(neigh.clone(), next_cost);
+            result.predecessors.insert(neigh.clone(), current.clone());
+            to_visit.push(HeapEntry {priority: next_cost + heuristic(&neigh), cost: next_cost, state: neigh});
+        }
+    }
+
+    result
+}
+
+// ordered so that BinaryHeap (a max-heap) pops the lowest priority first
+struct HeapEntry<S, C> {
+    priority: C,
+    cost: C,
+    state: S,
+}
+
+impl<S, C: Ord> PartialEq for HeapEntry<S, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<S, C: Ord> Eq for HeapEntry<S, C> {}
+
+impl<S, C: Ord> PartialOrd for HeapEntry<S, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S, C: Ord> Ord for HeapEntry<S, C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.cmp(&self.priority)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::days::points::Point2;
+
+    type Point = Point2<i32>;
+
+    // '#' are walls, costs of other cells are their digit (1 for '.')
+    const MAZE: [&str; 5] = [
+        "..#....",
+        ".##.##.",
+        "...9#..",
+        "#.#.#.#",
+        "...1...",
+    ];
+
+    fn maze_cell(p: &Point) -> Option<u32> {
+        let line = MAZE.get(usize::try_from(p.y).ok()?)?;
+        match line.as_bytes().get(usize::try_from(p.x).ok()?)? {
+            b'#' => None,
+            b'.' => Some(1),
+            c => Some((c - b'0') as u32),
+        }
+    }
+
+    fn maze_neighbors(p: &Point) -> Vec<Point> {
+        p.axis_neighbors().filter(|n| maze_cell(n).is_some()).collect()
+    }
+
+    #[test]
+    fn test_bfs() {
+        let start = Point::from_xy(0, 0);
+        let end = Point::from_xy(6, 4);
+        let res = bfs([start], maze_neighbors, |_| false);
+        assert_eq!(res.cost(&end), Some(10));
+        assert_eq!(res.cost(&Point::from_xy(3, 0)), Some(7));
+        assert_eq!(res.cost(&Point::from_xy(3, 3)), Some(6));
+        assert_eq!(res.cost(&Point::from_xy(2, 0)), None);
+        assert!(res.goal.is_none());
+
+        let path = res.path_to(&end).unwrap();
+        assert_eq!(path.len(), 11);
+        assert_eq!(path[0], start);
+        assert!(path.windows(2).all(|w| w[0].manhattan_distance(&w[1]) == 1));
+
+        let res = bfs([start], maze_neighbors, |p| p.y == 4);
+        assert_eq!(res.goal, Some(Point::from_xy(1, 4)));
+        assert_eq!(res.goal_cost(), Some(5));
+    }
+
+    #[test]
+    fn test_multi_source() {
+        let starts = [Point::from_xy(0, 0), Point::from_xy(6, 0)];
+        let res = bfs(starts, maze_neighbors, |_| false);
+        assert_eq!(res.cost(&Point::from_xy(3, 0)), Some(3));
+        assert_eq!(res.cost(&Point::from_xy(0, 4)), Some(6));
+        assert_eq!(res.path_to(&Point::from_xy(6, 0)), Some(vec![Point::from_xy(6, 0)]));
+    }
+
+    #[test]
+    fn test_dijkstra_and_a_star() {
+        let start = Point::from_xy(0, 0);
+        let end = Point::from_xy(6, 4);
+        let weighted_neighbors = |p: &Point| {
+            maze_neighbors(p).into_iter().map(|n| (n, maze_cell(&n).unwrap())).collect::<Vec<_>>()
+        };
+
+        let res = dijkstra([start], weighted_neighbors, |_| false);
+        assert_eq!(res.cost(&end), Some(10));
+        // going through the 9 is shorter but more expensive
+        assert_eq!(res.cost(&Point::from_xy(3, 3)), Some(8));
+        let path = res.path_to(&Point::from_xy(3, 3)).unwrap();
+        assert!(!path.contains(&Point::from_xy(3, 2)));
+
+        let res_a_star = a_star([start], weighted_neighbors, |p| p.manhattan_distance(&end) as u32, |p| *p == end);
+        assert_eq!(res_a_star.goal_cost(), Some(10));
+        assert!(res_a_star.costs.len() <= res.costs.len());
+        let path = res_a_star.path_to(&end).unwrap();
+        let path_cost: u32 = path.iter().skip(1).map(|p| maze_cell(p).unwrap()).sum();
+        assert_eq!(path_cost, 10);
+    }
+}
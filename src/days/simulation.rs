@@ -0,0 +1,163 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    Continue,
+    /// The simulation reached its end during this step
+    Done,
+}
+
+/// Discrete simulation advanced one step at a time, `is_done` must be true once `step` returned `Done`
+pub trait Simulation {
+    fn step(&mut self) -> StepOutcome;
+    /// Text drawing of the current state
+    fn render(&self) -> String;
+    fn is_done(&self) -> bool;
+}
+
+/// Step until done, returns the number of steps
+pub fn run_to_end<S>(sim: &mut S) -> usize
+where S: Simulation
+{
+    let mut num_steps = 0;
+    while !sim.is_done() {
+        num_steps += 1;
+        if sim.step() == StepOutcome::Done {
+            break;
+        }
+    }
+    num_steps
+}
+
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    /// Draw every step, redrawing in place with ANSI escape codes
+    pub animate: bool,
+    pub frame_rate: f64,
+    /// Stop after this many steps, even if the simulation is not done
+    pub stop_at: Option<usize>,
+    /// Steps whose drawing is written to `snapshot_dir`, 0 being the initial state
+    pub snapshot_steps: Vec<usize>,
+    pub snapshot_dir: PathBuf,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self
+    {
+        Self {
+            animate: false,
+            frame_rate: 10.0,
+            stop_at: None,
+            snapshot_steps: Vec::new(),
+            snapshot_dir: PathBuf::from("."),
+        }
+    }
+}
+
+/// Step until done or `options.stop_at`, returns the number of steps
+pub fn run<S, W>(sim: &mut S, options: &RunOptions, out: &mut W) -> io::Result<usize>
+where S: Simulation,
+W: Write
+{
+    let frame_duration = Duration::from_secs_f64(1.0 / options.frame_rate.max(0.001));
+    let mut num_steps = 0;
+    let mut num_drawn_lines = 0;
+
+    loop {
+        let frame_start = Instant::now();
+        if options.snapshot_steps.contains(&num_steps) {
+            fs::create_dir_all(&options.snapshot_dir)?;
+            fs::write(options.snapshot_dir.join(format!("step_{num_steps}.txt")), sim.render())?;
+        }
+        if options.animate {
+            num_drawn_lines = draw_frame(out, &sim.render(), num_steps, num_drawn_lines)?;
+        }
+
+        let reached_stop = options.stop_at.is_some_and(|stop_at| num_steps >= stop_at);
+        if reached_stop || sim.is_done() {
+            break;
+        }
+        num_steps += 1;
+        sim.step();
+
+        if options.animate {
+            thread::sleep(frame_duration.saturating_sub(frame_start.elapsed()));
+        }
+    }
+
+    Ok(num_steps)
+}
+
+// draw over the previous frame, returns the number of lines drawn
+fn draw_frame<W>(out: &mut W, frame: &str, step: usize, num_previous_lines: usize) -> io::Result<usize>
+where W: Write
+{
+    if num_previous_lines > 0 {
+        // back to the start of the previous frame
+        write!(out, "\x1b[{num_previous_lines}F")?;
+    }
+    let mut num_lines = 0;
+    for line in frame.lines().chain([format!("step {step}").as_str()]) {
+        // erase what remains of the previous frame on this line
+        writeln!(out, "{line}\x1b[K")?;
+        num_lines += 1;
+    }
+    // and below the frame if it got shorter
+    write!(out, "\x1b[J")?;
+    out.flush()?;
+    Ok(num_lines)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Countdown(usize);
+
+    impl Simulation for Countdown {
+        fn step(&mut self) -> StepOutcome {
+            self.0 -= 1;
+            if self.0 == 0 { StepOutcome::Done } else { StepOutcome::Continue }
+        }
+
+        fn render(&self) -> String {
+            format!("{}\n", self.0)
+        }
+
+        fn is_done(&self) -> bool {
+            self.0 == 0
+        }
+    }
+
+    #[test]
+    fn test_run() {
+        assert_eq!(run_to_end(&mut Countdown(5)), 5);
+        assert_eq!(run_to_end(&mut Countdown(0)), 0);
+
+        let snapshot_dir = std::env::temp_dir().join(format!("simulation_test_{}", std::process::id()));
+        let options = RunOptions {
+            animate: true,
+            frame_rate: 1000.0,
+            stop_at: Some(3),
+            snapshot_steps: vec![0, 2, 10],
+            snapshot_dir: snapshot_dir.clone(),
+        };
+        let mut out = Vec::new();
+        let mut sim = Countdown(5);
+        assert_eq!(run(&mut sim, &options, &mut out).unwrap(), 3);
+        assert_eq!(sim.0, 2);
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("5\x1b[K\nstep 0\x1b[K\n\x1b[J\x1b[2F4\x1b[K\n"));
+        assert!(out.ends_with("2\x1b[K\nstep 3\x1b[K\n\x1b[J"));
+
+        assert_eq!(fs::read_to_string(snapshot_dir.join("step_0.txt")).unwrap(), "5\n");
+        assert_eq!(fs::read_to_string(snapshot_dir.join("step_2.txt")).unwrap(), "3\n");
+        assert!(!snapshot_dir.join("step_10.txt").exists());
+        fs::remove_dir_all(snapshot_dir).unwrap();
+    }
+}
@@ -0,0 +1,200 @@
+use std::cmp::Ordering;
+
+/// Order used by `TopK` to decide which items are the best
+pub trait Compare<T> {
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+/// Greatest items are the best
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Highest;
+
+/// Smallest items are the best
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lowest;
+
+impl<T: Ord> Compare<T> for Highest {
+    fn compare(&self, a: &T, b: &T) -> Ordering
+    {
+        a.cmp(b)
+    }
+}
+
+impl<T: Ord> Compare<T> for Lowest {
+    fn compare(&self, a: &T, b: &T) -> Ordering
+    {
+        b.cmp(a)
+    }
+}
+
+impl<T, F> Compare<T> for F
+where F: Fn(&T, &T) -> Ordering
+{
+    fn compare(&self, a: &T, b: &T) -> Ordering
+    {
+        self(a, b)
+    }
+}
+
+/// Keeps the `k` best items pushed so far, in O(log k) per push
+#[derive(Debug, Clone)]
+pub struct TopK<T, C = Highest> {
+    // binary heap with the worst kept item at the root
+    heap: Vec<T>,
+    k: usize,
+    comparator: C,
+}
+
+pub type BottomK<T> = TopK<T, Lowest>;
+
+impl<T, C> TopK<T, C>
+where C: Compare<T> + Default
+{
+    pub fn new(k: usize) -> Self
+    {
+        Self::with_comparator(k, C::default())
+    }
+}
+
+impl<T, C> TopK<T, C>
+where C: Compare<T>
+{
+    pub fn with_comparator(k: usize, comparator: C) -> Self
+    {
+        Self {
+            heap: Vec::with_capacity(k),
+            k,
+            comparator,
+        }
+    }
+
+    /// Maximum number of kept items
+    pub fn k(&self) -> usize
+    {
+        self.k
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.heap.is_empty()
+    }
+
+    /// Worst of the kept items, a new item must beat it to be kept once `k` items are kept
+    pub fn worst(&self) -> Option<&T>
+    {
+        self.heap.first()
+    }
+
+    pub fn push(&mut self, item: T)
+    {
+        if self.heap.len() < self.k {
+            self.heap.push(item);
+            self.sift_up(self.heap.len() - 1);
+        }
+        else if self.k > 0 && self.is_better(&item, &self.heap[0]) {
+            self.heap[0] = item;
+            self.sift_down(0);
+        }
+    }
+
+    /// Kept items, in no particular order
+    pub fn iter(&self) -> std::slice::Iter<'_, T>
+    {
+        self.heap.iter()
+    }
+
+    /// Kept items, best first
+    pub fn into_sorted_vec(self) -> Vec<T>
+    {
+        let Self { mut heap, comparator, .. } = self;
+        heap.sort_by(|a, b| comparator.compare(b, a));
+        heap
+    }
+
+    fn is_better(&self, a: &T, b: &T) -> bool
+    {
+        self.comparator.compare(a, b) == Ordering::Greater
+    }
+
+    fn sift_up(&mut self, mut idx: usize)
+    {
+        while idx > 0 {
+            let parent = (idx - 1) / 2;
+            if !self.is_better(&self.heap[parent], &self.heap[idx]) {
+                break;
+            }
+            self.heap.swap(parent, idx);
+            idx = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut idx: usize)
+    {
+        loop {
+            let mut worst = idx;
+            for child in [2 * idx + 1, 2 * idx + 2] {
+                if child < self.heap.len() && self.is_better(&self.heap[worst], &self.heap[child]) {
+                    worst = child;
+                }
+            }
+            if worst == idx {
+                break;
+            }
+            self.heap.swap(worst, idx);
+            idx = worst;
+        }
+    }
+}
+
+impl<T, C> Extend<T> for TopK<T, C>
+where C: Compare<T>
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I)
+    {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const VALUES: [i32; 10] = [5, -3, 12, 7, 7, 0, 42, -8, 19, 3];
+
+    #[test]
+    fn test_highest() {
+        let mut top: TopK<i32> = TopK::new(3);
+        top.extend(VALUES);
+        assert_eq!((top.len(), top.k()), (3, 3));
+        assert_eq!(top.worst(), Some(&12));
+        assert_eq!(top.iter().sum::<i32>(), 42 + 19 + 12);
+        assert_eq!(top.into_sorted_vec(), vec![42, 19, 12]);
+
+        let mut top: TopK<i32> = TopK::new(20);
+        top.extend(VALUES);
+        assert_eq!(top.len(), VALUES.len());
+        assert_eq!(top.into_sorted_vec()[..4], [42, 19, 12, 7]);
+
+        let mut top: TopK<i32> = TopK::new(0);
+        top.push(1);
+        assert!(top.is_empty());
+    }
+
+    #[test]
+    fn test_lowest_and_comparator() {
+        let mut bottom: BottomK<i32> = BottomK::new(4);
+        bottom.extend(VALUES);
+        assert_eq!(bottom.into_sorted_vec(), vec![-8, -3, 0, 3]);
+
+        let mut closest_to_zero = TopK::with_comparator(2, |a: &i32, b: &i32| b.abs().cmp(&a.abs()));
+        closest_to_zero.extend(VALUES);
+        assert_eq!(closest_to_zero.into_sorted_vec(), vec![0, -3]);
+    }
+}